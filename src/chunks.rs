@@ -0,0 +1,107 @@
+use bevy::prelude::UVec3;
+
+use crate::grid::{coords, linear_index, volume};
+
+// side length of a chunk in cells - the last chunk per axis may be cut short
+pub const CHUNK: usize = 8;
+
+// Keeps track of which CHUNK^3 blocks of the grid have to be re-evaluated next generation.
+// A cell can only change if something within its neighborhood changed last generation,
+// so only chunks that changed plus the chunks within neighbor reach of them are active
+#[derive(Clone, Debug)]
+pub struct ActiveChunks {
+    size: UVec3,    // in cells
+    per_axis: UVec3, // in chunks
+    reach: usize, // how many cells away a change can still have an effect
+    wrap: bool,   // torus - reach continues on the opposite face
+    changed: Vec<bool>,
+    all: bool,    // everything counts as changed e.g. fresh or replaced grid
+}
+
+impl ActiveChunks {
+    // neighbor_reach is the largest distance (per axis) of any neighbor offset
+    pub fn new(size: UVec3, neighbor_reach: usize, wrap: bool) -> ActiveChunks {
+        let c = CHUNK as u32;
+        let per_axis = UVec3::new(size.x.div_ceil(c), size.y.div_ceil(c), size.z.div_ceil(c));
+        ActiveChunks {
+            size,
+            per_axis,
+            reach: neighbor_reach,
+            wrap,
+            changed: vec![false; volume(per_axis)],
+            all: true,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.changed.len()
+    }
+
+    pub fn mark_all(&mut self) {
+        self.all = true;
+    }
+
+    // replaces the changed chunks with the ones from the last generation
+    pub fn set_changed(&mut self, changed: impl IntoIterator<Item = usize>) {
+        self.all = false;
+        self.changed.fill(false);
+        for c in changed {
+            self.changed[c] = true;
+        }
+    }
+
+    // chunk indices to evaluate next - the changed chunks grown by the neighbor reach
+    pub fn active(&self) -> Vec<usize> {
+        if self.all {
+            return (0..self.total()).collect();
+        }
+
+        let n = self.per_axis;
+        let mut active = vec![false; self.total()];
+        for c in (0..self.total()).filter(|&c| self.changed[c]) {
+            let (cx, cy, cz) = coords(c, n);
+            let (xs, ys, zs) = (self.around(cx, 0), self.around(cy, 1), self.around(cz, 2));
+            for &x in &xs {
+                for &y in &ys {
+                    for &z in &zs {
+                        active[linear_index(x, y, z, n)] = true;
+                    }
+                }
+            }
+        }
+        (0..self.total()).filter(|&c| active[c]).collect()
+    }
+
+    // chunk coordinates along the axis with cells within reach of chunk coordinate c. A torus wraps in cells -
+    // the last chunk may be cut short, so a few cells back over the face can already be two chunks away
+    fn around(&self, c: usize, axis: usize) -> Vec<usize> {
+        let size = self.size.to_array()[axis] as isize;
+        let reach = self.reach as isize;
+        let lo = (c * CHUNK) as isize - reach;
+        let hi = (((c + 1) * CHUNK) as isize).min(size) + reach;
+        let mut chunks = Vec::new();
+        for v in lo..hi {
+            let v = if self.wrap {
+                v.rem_euclid(size)
+            } else if v >= 0 && v < size {
+                v
+            } else {
+                continue;
+            };
+            let chunk = v as usize / CHUNK;
+            if !chunks.contains(&chunk) {
+                chunks.push(chunk);
+            }
+        }
+        chunks
+    }
+
+    // cell ranges covered by a chunk - lower bounds inclusive, upper exclusive
+    pub fn bounds(&self, chunk: usize) -> ([usize; 3], [usize; 3]) {
+        let (cx, cy, cz) = coords(chunk, self.per_axis);
+        let lo = [cx * CHUNK, cy * CHUNK, cz * CHUNK];
+        let size = self.size.to_array();
+        let hi = std::array::from_fn(|a| (lo[a] + CHUNK).min(size[a] as usize));
+        (lo, hi)
+    }
+}
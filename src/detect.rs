@@ -0,0 +1,66 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use bevy::prelude::Event;
+
+// cycles longer than this many generations aren't noticed
+pub const MAX_PERIOD: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Extinct,   // nothing left at all
+    StillLife, // same state every generation
+    Cycle,     // repeats every period generations
+}
+
+// sent once a soup has settled into one of the outcomes
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Settled {
+    pub outcome: Outcome,
+    pub period: usize, // 1 for extinct and still lifes
+    pub start: usize,  // first generation in the repeating state(s)
+}
+
+impl fmt::Display for Settled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.outcome {
+            Outcome::Extinct => write!(f, "extinct at {}", self.start),
+            Outcome::StillLife => write!(f, "still life since {}", self.start),
+            Outcome::Cycle => write!(f, "period {} cycle since {}", self.period, self.start),
+        }
+    }
+}
+
+// Remembers the state hashes of the last MAX_PERIOD generations - a state coming up again means
+// the world is in a cycle from then on as every generation only depends on the one before
+#[derive(Clone, Debug, Default)]
+pub struct Detector {
+    seen: HashMap<u64, usize>, // state hash => generation
+    order: VecDeque<u64>,      // hashes oldest first - to forget them again
+}
+
+impl Detector {
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+
+    // feeds the next generation - has to be called for consecutive generations
+    pub fn observe(&mut self, hash: u64, generation: usize, empty: bool) -> Option<Settled> {
+        if empty {
+            return Some(Settled { outcome: Outcome::Extinct, period: 1, start: generation });
+        }
+        if let Some(&start) = self.seen.get(&hash) {
+            let period = generation - start;
+            let outcome = if period == 1 { Outcome::StillLife } else { Outcome::Cycle };
+            return Some(Settled { outcome, period, start });
+        }
+
+        self.seen.insert(hash, generation);
+        self.order.push_back(hash);
+        if self.order.len() > MAX_PERIOD && let Some(old) = self.order.pop_front() {
+            self.seen.remove(&old);
+        }
+        None
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use bevy::prelude::UVec3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::detect::Outcome;
+use crate::game::{Boundary, Game};
+use crate::headless::{find, parse_flags, parse_size, ArgError};
+use crate::rule::{CountSet, Rule, RuleError};
+use crate::seeding::Seeding;
+
+pub const USAGE: &str = "\
+usage: game_of_life_bevy --explore [options]
+
+  --sample N            try N random rules (default 100)
+  --ranges MAX          try every B lo-hi / S lo-hi rule with counts up to MAX instead
+  --soups N             seeded soups per rule
+  --size N | X,Y,Z      cells per axis
+  --prob P              spawn probability of the soups (0 to 1)
+  --boundary NAME       Torus, Dead, Alive, Mirror or Unbounded
+  --generations N       generations per soup - a soup that settles stops earlier
+  --seed N              seed of the rule sampling and the soups - random if left out
+  --report FILE         where the ranked rules go - rules_<seed>.csv otherwise";
+
+// flags followed by a value
+const VALUE_FLAGS: [&str; 9] = [
+    "--sample", "--ranges", "--soups", "--size", "--prob", "--boundary", "--generations", "--seed", "--report",
+];
+
+// an unsettled soup denser than this at the end counts as exploded
+const EXPLODED: f64 = 0.3;
+// and so does one boiling - more births + deaths than this per alive cell and generation towards the end
+const BOILING: f64 = 0.5;
+// highest neighbor count of the Moore neighborhood the sampled rules use
+const COUNTS: usize = 26;
+// how many rules of a report show up in the menu (`--rules report.csv`)
+pub const TOP: usize = 5;
+
+// what became of a soup
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Class {
+    Dies,       // went extinct
+    Explodes,   // still changing but filling up the world or boiling all over
+    Stabilizes, // froze into a still life
+    Oscillates, // repeats with a period above 1
+    Complex,    // still changing at a moderate density and pace when the generations ran out
+}
+
+impl Class {
+    pub const ALL: [Class; 5] = [Class::Dies, Class::Explodes, Class::Stabilizes, Class::Oscillates, Class::Complex];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::Dies => "dies",
+            Class::Explodes => "explodes",
+            Class::Stabilizes => "stabilizes",
+            Class::Oscillates => "oscillates",
+            Class::Complex => "complex",
+        }
+    }
+
+    // how interesting the outcome is - what the rules get ranked by
+    fn weight(&self) -> f64 {
+        match self {
+            Class::Dies | Class::Explodes => 0.,
+            Class::Stabilizes => 0.2,
+            Class::Oscillates => 0.5,
+            Class::Complex => 1.,
+        }
+    }
+}
+
+// one soup run under one rule
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Trial {
+    pub class: Class,
+    pub population: usize, // at the end
+    pub peak: usize,       // highest population on the way
+    pub period: usize,     // of the cycle it settled into - 0 when it didn't settle
+    pub churn: f64,        // births + deaths per alive cell and generation over the last quarter of the run
+}
+
+impl Trial {
+    // runs until the game settles or the generations run out
+    pub fn run(game: &mut Game, generations: usize) -> Trial {
+        while game.generation < generations && game.settled.is_none() {
+            game.advance_state();
+        }
+
+        let series = game.stats.series();
+        let population = series.last().map_or(0, |row| row.population);
+        let peak = series.iter().map(|row| row.population).max().unwrap_or(0);
+        let density = series.last().map_or(0., |row| row.density);
+        // the first row has no births / deaths - it's the starting soup
+        let start = (series.len() - series.len() / 4).min(series.len().saturating_sub(1)).max(1);
+        let tail = series.get(start..).unwrap_or_default();
+        let churn = match tail.len() {
+            0 => 0.,
+            n => tail.iter().map(|row| (row.births + row.deaths) as f64 / row.population.max(1) as f64).sum::<f64>() / n as f64,
+        };
+        let (class, period) = match game.settled {
+            Some(settled) => match settled.outcome {
+                Outcome::Extinct => (Class::Dies, 1),
+                Outcome::StillLife => (Class::Stabilizes, 1),
+                Outcome::Cycle => (Class::Oscillates, settled.period),
+            },
+            None if density > EXPLODED || churn > BOILING => (Class::Explodes, 0),
+            None => (Class::Complex, 0),
+        };
+        Trial { class, population, peak, period, churn }
+    }
+}
+
+// a rule with the trials of all its soups
+#[derive(Clone, Debug)]
+pub struct Ranked {
+    pub rule: Rule,
+    pub trials: Vec<Trial>,
+    pub score: f64, // mean weight of the trials' classes - 0 (boring) to 1 (complex every time)
+}
+
+impl Ranked {
+    fn new(rule: Rule, trials: Vec<Trial>) -> Ranked {
+        let score = trials.iter().map(|t| t.class.weight()).sum::<f64>() / trials.len().max(1) as f64;
+        Ranked { rule, trials, score }
+    }
+
+    pub fn count(&self, class: Class) -> usize {
+        self.trials.iter().filter(|t| t.class == class).count()
+    }
+}
+
+// "dies 1, complex 2" - classes that came up
+impl fmt::Display for Ranked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} {}", self.score, self.rule)?;
+        let mut first = true;
+        for class in Class::ALL {
+            let n = self.count(class);
+            if n > 0 {
+                write!(f, "{} {} {}", if first { " -" } else { "," }, class.name(), n)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+// which rules get tried
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sampling {
+    Random(usize), // this many random rules
+    Ranges(usize), // every rule with one range of counts for birth and one for survival, up to this count
+}
+
+// Rule space explorer - `--explore` on the command line, no window either:
+//
+//   game_of_life_bevy --explore --sample 200 --soups 3 --seed 42 --report rules.csv
+//
+// Runs 2 state B/S rules (Moore neighborhood) from a few seeded soups each and ranks them by what
+// became of the soups. The report is a CSV with the rule in the last column (rules have commas) -
+// `--rules rules.csv` puts the best ones into the menu of the viewer, `--rule "..."` picks a single one
+#[derive(Clone, Debug)]
+pub struct Explorer {
+    pub sampling: Sampling,
+    pub soups: usize,
+    pub size: UVec3,
+    pub prob: f64,
+    pub boundary: Boundary,
+    pub generations: usize,
+    pub seed: u64,
+    pub report_path: Option<String>,
+}
+
+impl Explorer {
+    // small torus worlds by default - explodes are noticed quickly and nothing hits a wall
+    pub fn new(seed: u64) -> Explorer {
+        Explorer {
+            sampling: Sampling::Random(100),
+            soups: 3,
+            size: UVec3::splat(24),
+            prob: 0.1,
+            boundary: Boundary::Torus,
+            generations: 150,
+            seed,
+            report_path: None,
+        }
+    }
+
+    pub fn parse_args(mut self, args: &[String]) -> Result<Explorer, ArgError> {
+        parse_flags(args, &VALUE_FLAGS, |flag, value| {
+            let Some(value) = value else {
+                return match flag.as_str() {
+                    "--explore" => Ok(()),
+                    _ => Err(ArgError::UnknownFlag(flag.clone())),
+                };
+            };
+
+            let bad = || ArgError::BadValue(flag.clone(), value.clone());
+            match flag.as_str() {
+                "--sample" => self.sampling = Sampling::Random(value.parse().map_err(|_| bad())?),
+                "--ranges" => self.sampling = Sampling::Ranges(value.parse().ok().filter(|&n| n <= COUNTS).ok_or_else(bad)?),
+                "--soups" => self.soups = value.parse().ok().filter(|&n| n > 0).ok_or_else(bad)?,
+                "--size" => self.size = parse_size(value).ok_or_else(bad)?,
+                "--prob" => self.prob = value.parse().ok().filter(|p| (0. ..=1.).contains(p)).ok_or_else(bad)?,
+                "--boundary" => self.boundary = find(&Boundary::ALL, value).ok_or_else(bad)?,
+                "--generations" => self.generations = value.parse().map_err(|_| bad())?,
+                "--seed" => self.seed = value.parse().map_err(|_| bad())?,
+                "--report" => self.report_path = Some(value.clone()),
+                _ => unreachable!("{} is missing above", flag),
+            }
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    // the rules to try, without duplicates - birth never includes 0 as such a rule fills the world at once
+    pub fn rules(&self) -> Vec<Rule> {
+        match self.sampling {
+            Sampling::Ranges(max) => {
+                let ranges = |from: usize| (from..=max).flat_map(move |lo| (lo..=max).map(move |hi| CountSet::range(lo, hi)));
+                ranges(1).flat_map(|birth| ranges(0).map(move |survival| Rule::new(birth, survival))).collect()
+            }
+            Sampling::Random(n) => {
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                let mut seen = HashSet::new();
+                let mut rules = Vec::with_capacity(n);
+                // gives up on finding new ones after a while - the space is large but not endless
+                for _ in 0..n * 20 {
+                    if rules.len() == n {
+                        break;
+                    }
+                    let rule = Rule::new(random_counts(&mut rng, 1, 10, 3), random_counts(&mut rng, 0, 12, 6));
+                    if seen.insert(rule.clone()) {
+                        rules.push(rule);
+                    }
+                }
+                rules
+            }
+        }
+    }
+
+    // tries every rule, best first - done is called after each one with the number tried so far
+    pub fn explore(&self, mut done: impl FnMut(usize, usize, &Ranked)) -> Vec<Ranked> {
+        let rules = self.rules();
+        let mut ranked = Vec::with_capacity(rules.len());
+        for (i, rule) in rules.iter().enumerate() {
+            let trials = (0..self.soups as u64)
+                .map(|soup| {
+                    let seed = self.seed.wrapping_add(soup);
+                    let mut game = Game::new(self.size, self.prob, rule.clone(), 1., self.boundary, seed, Seeding::Random);
+                    Trial::run(&mut game, self.generations)
+                })
+                .collect();
+            ranked.push(Ranked::new(rule.clone(), trials));
+            done(i + 1, rules.len(), ranked.last().unwrap());
+        }
+        // stable - equal scores keep the order they were tried in
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        ranked
+    }
+
+    // ranked CSV - header lines record how the rules were tried
+    pub fn report(&self, ranked: &[Ranked]) -> String {
+        let mut csv = String::new();
+        let _ = writeln!(csv, "# soups: {}", self.soups);
+        let _ = writeln!(csv, "# seed: {}", self.seed);
+        let _ = writeln!(csv, "# size: {}x{}x{}", self.size.x, self.size.y, self.size.z);
+        let _ = writeln!(csv, "# boundary: {}", self.boundary.name());
+        let _ = writeln!(csv, "# prob: {}", self.prob);
+        let _ = writeln!(csv, "# generations: {}", self.generations);
+        csv.push_str("rank,score,dies,explodes,stabilizes,oscillates,complex,mean_population,mean_churn,longest_period,rule\n");
+        for (rank, r) in ranked.iter().enumerate() {
+            let mean = r.trials.iter().map(|t| t.population).sum::<usize>() as f64 / r.trials.len().max(1) as f64;
+            let churn = r.trials.iter().map(|t| t.churn).sum::<f64>() / r.trials.len().max(1) as f64;
+            let period = r.trials.iter().map(|t| t.period).max().unwrap_or(0);
+            let _ = write!(csv, "{},{:.3}", rank + 1, r.score);
+            for class in Class::ALL {
+                let _ = write!(csv, ",{}", r.count(class));
+            }
+            let _ = writeln!(csv, ",{:.1},{:.3},{},{}", mean, churn, period, r.rule);
+        }
+        csv
+    }
+}
+
+// small counts are where the interesting 3d rules are - a narrow range starting at from..=to,
+// sometimes with a lone extra count
+fn random_counts(rng: &mut StdRng, from: usize, to: usize, width: usize) -> CountSet {
+    let lo = rng.gen_range(from..=to);
+    let set = CountSet::range(lo, (lo + rng.gen_range(0..=width)).min(COUNTS));
+    if rng.gen_bool(0.3) {
+        let extra = rng.gen_range(from..=COUNTS);
+        set.union(CountSet::range(extra, extra))
+    } else {
+        set
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportError {
+    Io(String),
+    BadLine(usize),           // line without the rule column
+    BadRule(usize, RuleError), // line and what's wrong with its rule
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportError::Io(e) => write!(f, "couldn't read report: {}", e),
+            ReportError::BadLine(line) => write!(f, "line {} of the report has no rule", line),
+            ReportError::BadRule(line, e) => write!(f, "bad rule on line {} of the report: {}", line, e),
+        }
+    }
+}
+
+impl std::error::Error for ReportError {}
+
+// the first `top` rules of a report written by Explorer::report
+pub fn load_report(path: &str, top: usize) -> Result<Vec<Rule>, ReportError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ReportError::Io(e.to_string()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.starts_with('#') && !l.starts_with("rank,"))
+        .take(top)
+        .map(|(n, line)| {
+            // the rule is everything after the 10th comma
+            let rule = line.splitn(11, ',').nth(10).ok_or(ReportError::BadLine(n + 1))?;
+            rule.trim().parse().map_err(|e| ReportError::BadRule(n + 1, e))
+        })
+        .collect()
+}
@@ -1,183 +1,551 @@
 use bevy::prelude::*;
-use rand::Rng;
-
-#[derive(Clone, Copy, PartialEq, PartialOrd,Debug)]
-pub enum RuleSet {
-    Balanced,
-    Dense,
-    Sparse,
-    Chaotic,
-    NoDeath,
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::chunks::ActiveChunks;
+use crate::detect::{Detector, Outcome, Settled};
+use crate::grid::{volume, Grid};
+use crate::hashlife::HashLife;
+use crate::history::{Change, History, Step};
+use crate::pattern::{Pattern, PatternError};
+use crate::lenia::{Lenia, LeniaParams};
+use crate::rule::{noise, CountSet, Rule, ALIVE, DEAD};
+use crate::seeding::Seeding;
+use crate::sparse::{SparseWorld, CHUNK_CELLS};
+use crate::stats::{GenStats, Stats};
+
+// what lies beyond the faces of the cube when counting neighbors
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Boundary {
+    Torus,  // wraps around to the opposite face
+    #[default]
+    Dead,   // everything outside is dead
+    Alive,  // everything outside is alive
+    Mirror, // outside mirrors the cells just inside the face
+    Unbounded, // no faces - the world grows in chunks as the pattern spreads (see SparseWorld)
+}
+
+impl Boundary {
+    // boundaries with their display names - used for the menu
+    pub const ALL: [(&'static str, Boundary); 5] = [
+        ("Torus", Boundary::Torus),
+        ("Dead", Boundary::Dead),
+        ("Alive", Boundary::Alive),
+        ("Mirror", Boundary::Mirror),
+        ("Unbounded", Boundary::Unbounded),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Boundary::ALL.iter().find(|(_, b)| b == self).map(|(name, _)| *name).unwrap_or_default()
+    }
+
+    // maps a possibly out of range coordinate back into 0..size - None => outside the world
+    #[inline]
+    fn resolve(self, v: isize, size: isize) -> Option<usize> {
+        if v >= 0 && v < size {
+            return Some(v as usize);
+        }
+        match self {
+            Boundary::Dead | Boundary::Alive | Boundary::Unbounded => None,
+            Boundary::Torus => Some(v.rem_euclid(size) as usize),
+            Boundary::Mirror => {
+                // -1 => 0, -2 => 1, size => size-1 ... repeating every 2*size
+                let m = v.rem_euclid(2 * size);
+                Some(if m < size { m } else { 2 * size - 1 - m } as usize)
+            }
+        }
+    }
 }
 
 #[derive(Resource)]
 pub struct Game {
-    pub grid: Vec<Vec<Vec<bool>>>,
+    pub grid: Grid, // cell states - DEAD, ALIVE or dying for Generations rules
     pub generation: usize,
     pub first_disp: bool,
-    pub cell_count: usize ,// per dim i.e. for cc = 16 => 16x16x16
+    pub size: UVec3, // cells per axis i.e. for (256, 256, 8) => a 256x256x8 slab
     pub prob: f64,
-    pub rule: RuleSet,
-    pub speed: f32
+    pub rule: Rule,
+    pub speed: f32,
+    pub boundary: Boundary,
+    pub seed: u64, // the soup is fully determined by seed, prob and size - same seed => same soup
+    pub seeding: Seeding, // random soup or one of the structures
+    pub pattern: Option<(Pattern, [i64; 3])>, // stamped on top of the seeding at this offset - again on every reset
+    neighbors: Vec<([isize; 3], usize)>, // offsets and weights of the rule's neighborhood - cached as count_neighbors runs per cell
+    back: Grid, // next generation gets written here and then swapped with grid - no allocation per step
+    chunks: ActiveChunks,
+    pub active_chunks: usize, // chunks evaluated in the last step - out of chunks.total()
+    hashlife: Option<HashLife>, // kept between jumps so its caches carry over
+    pub sparse: Option<SparseWorld>, // the actual world for an unbounded boundary - grid only holds the starting soup then
+    pub history: History, // past (and stepped back over) generations
+    detector: Detector,
+    pub settled: Option<Settled>, // set once the world died out / froze / started repeating
+    pub lenia: Option<Lenia>, // continuous world instead of the rule - grid holds its cells above lenia::THRESHOLD then
+    pub stats: Stats, // numbers per generation
 }
-// todo : add "custom config" variations that allow spawning in specific structures rather than random generation
-impl Game {
 
-    pub fn new(size: usize, prob:f64, rule: RuleSet, speed: f32) -> Game {
-        let mut cell_count = 0;
+impl Game {
 
-        if size < 16 { cell_count = 16; } 
-        else { cell_count = size; } 
+    pub fn new(size: UVec3, prob:f64, rule: Rule, speed: f32, boundary: Boundary, seed: u64, seeding: Seeding) -> Game {
+        // at least one cell per axis - thin slabs like 256x256x8 are fine
+        let size = rule.neighborhood.lattice().fit(size.max(UVec3::ONE), boundary == Boundary::Torus);
 
+        let mut grid = seeding.generate(prob, size, rule.states, seed);
+        rule.neighborhood.lattice().mask(&mut grid);
 
-        // if rule matches specific structure - i.e. specific generated pattern - else randomize
-        let grid:Vec<Vec<Vec<bool>>> = match rule {
-            _ => Game::randomize(prob, cell_count)
-        };
+        let neighbors = rule.neighborhood.offsets();
+        let back = Grid::new(size, rule.states);
+        let chunks = Game::chunks_for(size, &neighbors, boundary);
+        let sparse = (boundary == Boundary::Unbounded).then(|| SparseWorld::from_grid(&grid));
 
-        Game {
+        let mut game = Game {
             grid,
             generation: 0,
             first_disp: true,
-            cell_count,
+            size,
             prob,
             rule,
-            speed
+            speed,
+            boundary,
+            seed,
+            seeding,
+            pattern: None,
+            neighbors,
+            back,
+            active_chunks: chunks.total(),
+            chunks,
+            hashlife: None,
+            sparse,
+            history: History::default(),
+            detector: Detector::default(),
+            settled: None,
+            lenia: None,
+            stats: Stats::default(),
+        };
+        game.detect();
+        game.measure(0, 0);
+        game
+    }
+
+    // a Lenia world (see lenia.rs) from a soup of the seed - rule and boundary aren't used, it always wraps around
+    pub fn new_lenia(size: UVec3, params: LeniaParams, speed: f32, seed: u64) -> Game {
+        let mut game = Game::new(size, 0., Rule::new(CountSet::EMPTY, CountSet::EMPTY), speed, Boundary::Torus, seed, Seeding::Empty);
+        game.lenia = Some(Lenia::new(params, game.size));
+        game.reset();
+        game
+    }
+
+    fn chunks_for(size: UVec3, neighbors: &[([isize; 3], usize)], boundary: Boundary) -> ActiveChunks {
+        let reach = neighbors.iter().flat_map(|(d, _)| d).map(|d| d.unsigned_abs()).max().unwrap_or(0);
+        ActiveChunks::new(size, reach, boundary == Boundary::Torus)
+    }
+
+    pub fn total_chunks(&self) -> usize {
+        match &self.sparse {
+            Some(world) => world.chunk_count(),
+            None => self.chunks.total(),
         }
     }
 
-    pub fn randomize(prob:f64,cell_count:usize) -> Vec<Vec<Vec<bool>>> {
-        let mut rng = rand::thread_rng();
-        let grid:Vec<Vec<Vec<bool>>> = (0..cell_count).map(|_| {
-            (0..cell_count).map(|_| {
-                (0..cell_count).map(|_| rng.gen_bool(prob)).collect() // Prob% chance true/false as alive cells should be rarer initially.collect()
-            }).collect()   
-        }).collect();
+    // a fresh seed for when none was given
+    pub fn random_seed() -> u64 {
+        rand::thread_rng().r#gen()
+    }
+
+    pub fn randomize(prob:f64, size: UVec3, states: u8, seed: u64) -> Grid {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut grid = Grid::new(size, states);
+        for x in 0..size.x as usize {
+            for y in 0..size.y as usize {
+                for z in 0..size.z as usize {
+                    // Prob% chance alive as alive cells should be rarer initially
+                    if rng.gen_bool(prob) {
+                        grid.set(x, y, z, ALIVE);
+                    }
+                }
+            }
+        }
         grid
     }
 
+    // back to generation 0 with the same soup
     pub fn reset(&mut self) {
-        let mut rng = rand::thread_rng();
+        self.grid = self.seeding.generate(self.prob, self.size, self.rule.states, self.seed);
+        self.rule.neighborhood.lattice().mask(&mut self.grid);
+        if self.sparse.is_some() {
+            self.sparse = Some(SparseWorld::from_grid(&self.grid));
+        }
+        if let Some(lenia) = &mut self.lenia {
+            *lenia = Lenia::soup(lenia.params, self.size, self.seed);
+            lenia.threshold(&mut self.grid);
+        }
+        self.stamp_pattern();
+        self.generation = 0;
+        self.stats.clear();
+        self.edited();
+    }
 
-        let grid:Vec<Vec<Vec<bool>>> = (0..self.cell_count).map(|_| {
-            (0..self.cell_count).map(|_| {
-                (0..self.cell_count).map(|_| rng.gen_bool(self.prob)).collect() 
-            }).collect()   
-        }).collect();
+    // puts a pattern into the world with its corner at offset - it stays there across resets.
+    // BadState if it has states the rule doesn't (Lenia only cares about alive or not)
+    pub fn stamp(&mut self, pattern: Pattern, offset: [i64; 3]) -> Result<(), PatternError> {
+        if self.lenia.is_none() {
+            pattern.check_states(self.rule.states)?;
+        }
+        self.pattern = Some((pattern, offset));
+        self.stamp_pattern();
+        self.edited();
+        Ok(())
+    }
 
-        self.grid = grid;
-        self.generation = 0;
+    fn stamp_pattern(&mut self) {
+        let Some((pattern, offset)) = &self.pattern else {
+            return;
+        };
+        // cells that would land off the lattice are left out
+        let pattern = &self.rule.neighborhood.lattice().restrict(pattern, *offset);
+        match (&mut self.sparse, &mut self.lenia) {
+            (Some(world), _) => pattern.stamp_sparse(world, *offset),
+            (None, Some(lenia)) => {
+                // alive cells of the pattern become full values
+                let mut cells = Grid::new(self.size, pattern.rule.as_ref().map_or(2, |r| r.states));
+                pattern.stamp(&mut cells, *offset);
+                for (x, y, z) in cells.iter_alive() {
+                    lenia.set(x, y, z, 1.);
+                }
+                lenia.threshold(&mut self.grid);
+            }
+            (None, None) => pattern.stamp(&mut self.grid, *offset),
+        }
+    }
+
+    // offset that puts the pattern in the middle of the world - lined up with the lattice
+    pub fn centered(&self, pattern: &Pattern) -> [i64; 3] {
+        let offset = std::array::from_fn(|a| (self.size.to_array()[a] as i64 - pattern.size.to_array()[a] as i64) / 2);
+        self.rule.neighborhood.lattice().align(pattern, offset)
+    }
+
+    // the live part of the world and where it is
+    pub fn capture(&self) -> (Pattern, [i64; 3]) {
+        // Lenia has no rule in that sense - its cells above the threshold are captured
+        let rule = self.lenia.is_none().then(|| self.rule.clone());
+        match &self.sparse {
+            Some(world) => Pattern::from_cells(world.iter_cells(), rule),
+            None => {
+                let size = self.size;
+                let cells = (0..size.x as usize).flat_map(move |x| {
+                    (0..size.y as usize).flat_map(move |y| (0..size.z as usize).map(move |z| (x, y, z)))
+                });
+                let cells = cells.map(|(x, y, z)| ([x as i64, y as i64, z as i64], self.grid.get(x, y, z)));
+                Pattern::from_cells(cells, rule)
+            }
+        }
+    }
+
+    // replaces all cells with the pattern's at the given generation - e.g. a loaded session
+    pub fn load_world(&mut self, world: &Pattern, offset: [i64; 3], generation: usize) {
+        self.grid.clear();
+        match &mut self.sparse {
+            Some(sparse) => {
+                *sparse = SparseWorld::default();
+                world.stamp_sparse(sparse, offset);
+            }
+            None => world.stamp(&mut self.grid, offset),
+        }
+        self.generation = generation;
+        self.stats.clear();
+        self.edited();
+    }
+
+    // cells were changed from outside the rule - history and detection start over from here
+    fn edited(&mut self) {
+        // back no longer trails grid by one generation - evaluate everything once
+        self.chunks.mark_all();
         self.first_disp = true;
+        self.history.clear();
+        self.redetect();
+        self.measure(0, 0);
+    }
+
+    // back to generation 0 with a new soup
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.reset();
     }
 
     pub fn advance_state(&mut self) {
         self.first_disp = false;
 
-        let mut new_grid:Vec<Vec<Vec<bool>>> = (0..self.cell_count).map(|_| {
-            (0..self.cell_count).map(|_| {
-                (0..self.cell_count).map(|_| false).collect() // just init empty vec 3d vec of false
-            }).collect()   
-        }).collect();
+        if let Some(lenia) = &mut self.lenia {
+            lenia.step();
+            std::mem::swap(&mut self.grid, &mut self.back);
+            lenia.threshold(&mut self.grid);
+            // no history - the values can't be restored from the cells above the threshold
+            let (births, deaths) = Change::between(&self.back, &self.grid).births_deaths();
+            self.generation +=1;
+            self.detect();
+            self.measure(births, deaths);
+            return;
+        }
 
-        for x in 0..self.grid.len() {
-            for y in 0..self.grid.len() {
-                for z in 0..self.grid.len() {
-                    // to get alive neighbors for each cell
-                    let count = self.count_neighbors(x, y, z);
-                    
-                    match self.rule {
-                        RuleSet::Balanced => self.balanced(count, &mut new_grid, x,y,z),
-                        RuleSet::Sparse => self.sparse(count, &mut new_grid, x,y,z),
-                        RuleSet::Dense => self.dense(count, &mut new_grid, x,y,z),
-                        RuleSet::Chaotic => self.chaotic(count, &mut new_grid, x,y,z),
-                        RuleSet::NoDeath => self.no_death(count, &mut new_grid, x,y,z),
+        if let Some(world) = &mut self.sparse {
+            let before = world.clone();
+            self.active_chunks = world.step(&self.rule, &self.neighbors, self.seed, self.generation);
+            let change = Change::World { before, after: world.clone() };
+            let (births, deaths) = change.births_deaths();
+            self.history.record(Step { from: self.generation, to: self.generation + 1, change });
+            self.generation +=1;
+            self.detect();
+            self.measure(births, deaths);
+            return;
+        }
+
+        // only chunks that can change are evaluated - everywhere else back still holds
+        // the previous generation which equals the current one there.
+        // Under a stochastic rule any cell can change at any time
+        if self.rule.is_stochastic() {
+            self.chunks.mark_all();
+        }
+        let active = self.chunks.active();
+        self.active_chunks = active.len();
+
+        // every cell only depends on the previous generation, so the chunks can be
+        // computed in any order / on any thread and still give the same result as a serial sweep
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let this = &*self;
+        let results = pool.scope(|s| {
+            for &chunk in &active {
+                s.spawn(async move { (chunk, this.step_chunk(chunk)) });
+            }
+        });
+
+        let mut changed = Vec::new();
+        for (chunk, (next, chunk_changed)) in results {
+            let ([x0, y0, z0], [x1, y1, z1]) = self.chunks.bounds(chunk);
+            let mut next = next.into_iter();
+            for x in x0..x1 {
+                for y in y0..y1 {
+                    for z in z0..z1 {
+                        self.back.set(x, y, z, next.next().unwrap_or_default());
                     }
-                    
                 }
             }
-        };
+            if chunk_changed {
+                changed.push(chunk);
+            }
+        }
+        self.chunks.set_changed(changed);
 
-        self.grid = new_grid;
+        std::mem::swap(&mut self.grid, &mut self.back);
+        // back holds the previous generation now
+        let change = Change::between(&self.back, &self.grid);
+        let (births, deaths) = change.births_deaths();
+        self.history.record(Step { from: self.generation, to: self.generation + 1, change });
         self.generation +=1;
+        self.detect();
+        self.measure(births, deaths);
     }
 
+    // appends the numbers of the current generation to the stats
+    fn measure(&mut self, births: usize, deaths: usize) {
+        let row = match &self.sparse {
+            // no fixed size - density is over the allocated chunks
+            Some(world) => GenStats::measure(self.generation, world.iter_alive(), world.chunk_count() * CHUNK_CELLS, births, deaths),
+            None => {
+                let alive = self.grid.iter_alive().map(|(x, y, z)| [x as i64, y as i64, z as i64]);
+                GenStats::measure(self.generation, alive, volume(self.size), births, deaths)
+            }
+        };
+        self.stats.push(row);
+    }
 
-    fn balanced(&self, count: usize, g: &mut Vec<Vec<Vec<bool>>>, x:usize,y:usize,z:usize) {
-        if self.grid[x][y][z] {
-            if count >= 5 && count <= 7 { g[x][y][z] = true }
-        } else {
-            if count == 6 || count == 5 {g[x][y][z] = true; }
-        }
+    // the stats as CSV - header records what produced them
+    pub fn stats_csv(&self) -> String {
+        self.stats.to_csv(&self.stats_header())
     }
 
-    fn sparse(&self,count: usize, g: &mut Vec<Vec<Vec<bool>>>, x:usize,y:usize,z:usize) {
-        if self.grid[x][y][z] {
-            if count >= 3 && count <= 5 { g[x][y][z] = true }
-        } else {
-            if  count == 5 {g[x][y][z] = true; }
-        }
+    pub fn stats_header(&self) -> [(&'static str, String); 5] {
+        [
+            ("rule", self.lenia.as_ref().map_or_else(|| self.rule.to_string(), |l| format!("Lenia {}", l.params))),
+            ("seed", self.seed.to_string()),
+            ("size", format!("{}x{}x{}", self.size.x, self.size.y, self.size.z)),
+            ("boundary", self.boundary.name().to_string()),
+            ("prob", self.prob.to_string()),
+        ]
     }
 
-    fn dense(&self,count: usize, g: &mut Vec<Vec<Vec<bool>>>, x:usize,y:usize,z:usize) {
-        if self.grid[x][y][z] {
-            if count >= 4 && count <= 9 { g[x][y][z] = true }
-        } else {
-            if count == 6 || count == 5 {g[x][y][z] = true; }
+    // checks the current generation against the previous ones - until something is found
+    fn detect(&mut self) {
+        if self.settled.is_some() {
+            return;
         }
-    }
-    
-    // todo: find better params
-    fn chaotic(&self,count: usize, g: &mut Vec<Vec<Vec<bool>>>, x:usize,y:usize,z:usize) {
-        if self.grid[x][y][z] {
-            if count >= 5 && count <= 8 { g[x][y][z] = true }
+        let (hash, empty) = match &self.sparse {
+            Some(world) => (world.state_hash(), world.chunk_count() == 0),
+            None => (self.grid.state_hash(), self.grid.is_empty()),
+        };
+        let extinct = Settled { outcome: Outcome::Extinct, period: 1, start: self.generation };
+        self.settled = if let Some(lenia) = &self.lenia {
+            // continuous values hardly ever repeat exactly - only a world without anything left stays that way
+            lenia.is_empty().then_some(extinct)
+        } else if self.rule.is_stochastic() {
+            // a state coming up again is no cycle when chance is involved - only a world that died out
+            // without a temperature to bring anything back stays the way it is
+            (empty && self.rule.odds.temperature == 0).then_some(extinct)
         } else {
-            if count == 6 || count == 5 {g[x][y][z] = true; }
-        }
+            self.detector.observe(hash, self.generation, empty)
+        };
     }
 
-    fn no_death(&self,count: usize, g: &mut Vec<Vec<Vec<bool>>>, x:usize,y:usize,z:usize) {
-        if self.grid[x][y][z] {
-            g[x][y][z] = true // a born cell never dies
-        } else {
-            if count == 5 {g[x][y][z] = true; }
+    // the generations seen so far are no longer the ones right before this one - start over from here
+    fn redetect(&mut self) {
+        self.detector.clear();
+        self.settled = None;
+        self.detect();
+    }
+
+    // undoes the last step - false when there's nothing retained to go back to
+    pub fn step_back(&mut self) -> bool {
+        let Some(step) = self.history.back() else {
+            return false;
+        };
+        Game::apply(&mut self.grid, &mut self.sparse, &step.change, false);
+        self.generation = step.from;
+        self.restored();
+        true
+    }
+
+    // redoes a stepped back over generation, or computes the next one if there is none
+    pub fn step_forward(&mut self) {
+        let Some(step) = self.history.forward() else {
+            self.advance_state();
+            // stepped outside of game_step - have the renderer pick it up
+            self.first_disp = true;
+            return;
+        };
+        Game::apply(&mut self.grid, &mut self.sparse, &step.change, true);
+        self.generation = step.to;
+        self.restored();
+    }
+
+    // moves through the retained history towards the given generation - never computes new ones
+    pub fn scrub_to(&mut self, generation: usize) {
+        let (_, newest) = self.history_range();
+        while generation < self.generation && self.step_back() {}
+        while generation > self.generation && self.generation < newest {
+            self.step_forward();
         }
     }
-    
-    // create more mode/rules for interesting structures/distributions
-    // these will not generate the grid randomly but rather place patterns into it 
-    // and overwrite/insert custom rules
 
-    // Rule Set - recommended 96x96x96
+    // oldest and newest generation reachable by stepping back / forward
+    pub fn history_range(&self) -> (usize, usize) {
+        self.history.range(self.generation)
+    }
 
+    fn apply(grid: &mut Grid, sparse: &mut Option<SparseWorld>, change: &Change, forward: bool) {
+        match change {
+            Change::Cells { index, before, after } => {
+                let states = if forward { after } else { before };
+                for (&i, &state) in index.iter().zip(states) {
+                    grid.set_at(i as usize, state);
+                }
+            }
+            Change::World { before, after } => {
+                *sparse = Some(if forward { after } else { before }.clone());
+            }
+        }
+    }
 
+    // grid was replaced from history - back no longer trails it by one generation
+    // and the renderer has to pick up the whole grid again
+    fn restored(&mut self) {
+        self.chunks.mark_all();
+        self.first_disp = true;
+        self.redetect();
+    }
 
+    // fast forward to the given generation - uses the hashlife engine when the rule and world allow it
+    // (see HashLife::supports), otherwise just steps there one generation at a time
+    pub fn jump_to(&mut self, generation: usize) {
+        if generation <= self.generation {
+            return;
+        }
 
+        if self.lenia.is_none() && HashLife::supports(&self.rule, self.boundary, self.size) {
+            let rule = &self.rule;
+            let engine = self.hashlife.get_or_insert_with(|| HashLife::new(rule.clone()));
+            let next = engine.advance(&self.grid, generation - self.generation);
+            // the whole jump is a single step in the history
+            let change = Change::between(&self.grid, &next);
+            let (births, deaths) = change.births_deaths();
+            self.history.record(Step { from: self.generation, to: generation, change });
+            self.grid = next;
+            self.generation = generation;
+            self.measure(births, deaths);
+            // skipped generations weren't seen - a cycle found before the jump still holds though
+            self.detector.clear();
+            self.detect();
+            // back no longer trails grid by one generation - evaluate everything once
+            self.chunks.mark_all();
+        } else {
+            while self.generation < generation {
+                self.advance_state();
+            }
+        }
+        // have the renderer pick up the whole grid again
+        self.first_disp = true;
+    }
 
-    pub fn count_neighbors(&mut self, x:usize,y:usize,z:usize ) -> usize {
-    
-        // enumarate all possible neighbor combinations i.e. 
-        // for cell a at (0,0,0) relative its neighbors 
-        // are in b in {-1,0,1} (all combos => 27-1 = 26)
-        let size = self.grid.len() as isize;
-        let mut count = 0;
-    
-        for dz in -1..=1 {
-            for dy in -1..=1 {
-                for dx in -1..=1 {
-                    if dx == 0 && dy == 0 && dz == 0 {
-                        continue; // skip self
+    // next states of all cells of the chunk in x, y, z order and whether any of them changed
+    fn step_chunk(&self, chunk: usize) -> (Vec<u8>, bool) {
+        let ([x0, y0, z0], [x1, y1, z1]) = self.chunks.bounds(chunk);
+        let mut next = Vec::with_capacity((x1 - x0) * (y1 - y0) * (z1 - z0));
+        let mut changed = false;
+        let lattice = self.rule.neighborhood.lattice();
+        for x in x0..x1 {
+            for y in y0..y1 {
+                for z in z0..z1 {
+                    if !lattice.contains([x as i64, y as i64, z as i64]) {
+                        next.push(DEAD);
+                        continue;
                     }
+                    // to get alive neighbors for each cell
+                    let sides = self.count_neighbors(x, y, z);
+                    let current = self.grid.get(x, y, z);
+                    let state = if self.rule.is_stochastic() {
+                        self.rule.next_noisy(current, sides, noise(self.seed, self.generation, [x as i64, y as i64, z as i64]))
+                    } else {
+                        self.rule.next_sided(current, sides)
+                    };
+                    changed |= state != current;
+                    next.push(state);
+                }
+            }
+        }
+        (next, changed)
+    }
+
+    // alive neighbors below, beside and above the cell (along y) - directional rules tell them apart, for all others they add up
+    pub fn count_neighbors(&self, x:usize,y:usize,z:usize ) -> [usize; 3] {
     
-                    let nx = x as isize + dx;
-                    let ny = y as isize + dy;
-                    let nz = z as isize + dz;
+        // go over the offsets of the rule's neighborhood i.e. 
+        // for cell a at (0,0,0) relative its moore radius 1 neighbors 
+        // are in b in {-1,0,1} (all combos => 27-1 = 26)
+        let size = self.size;
+        let mut count = [0; 3];
     
-                    if nx >= 0 && nx < size && ny >= 0 && ny < size && nz >= 0 && nz < size {
-                        if self.grid[nx as usize][ny as usize][nz as usize] {
-                            count += 1;
-                        }
-                    }
-                }
+        for &([dx, dy, dz], weight) in &self.neighbors {
+            let side = (dy.signum() + 1) as usize;
+            let nx = self.boundary.resolve(x as isize + dx, size.x as isize);
+            let ny = self.boundary.resolve(y as isize + dy, size.y as isize);
+            let nz = self.boundary.resolve(z as isize + dz, size.z as isize);
+
+            match (nx, ny, nz) {
+                // dying cells of a Generations rule don't count
+                (Some(nx), Some(ny), Some(nz)) => if self.grid.is_alive(nx, ny, nz) {
+                    count[side] += weight;
+                },
+                // outside the world - only counts for an alive boundary
+                _ => if self.boundary == Boundary::Alive {
+                    count[side] += weight;
+                },
             }
         }
         count
@@ -185,4 +553,57 @@ impl Game {
     
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunks::CHUNK;
+    use crate::grid::linear_index;
 
+    // stepping only the active chunks has to give the same worlds as sweeping every cell each generation
+    #[test]
+    fn active_chunks_match_full_sweep() {
+        let size = UVec3::new(21, 13, 10);
+        let boundaries = [Boundary::Torus, Boundary::Dead, Boundary::Alive, Boundary::Mirror];
+        for boundary in boundaries {
+            for (rule, seeding) in [(Rule::BALANCED, Seeding::Random), (Rule::BRAIN, Seeding::Random), (Rule::BAYS, Seeding::Glider)] {
+                let new = || Game::new(size, 0.3, rule.clone(), 1., boundary, 7, seeding);
+                let (mut active, mut full) = (new(), new());
+                for _ in 0..30 {
+                    active.advance_state();
+                    full.chunks.mark_all();
+                    full.advance_state();
+                    assert!(active.grid == full.grid, "{} with {} at generation {}", rule, boundary.name(), active.generation);
+                }
+            }
+        }
+    }
+
+    // a reach of 2 across the seam of a torus whose sides aren't whole chunks - the short last chunk
+    // puts cells two chunks back within reach of the first one
+    #[test]
+    fn active_chunks_match_full_sweep_across_the_seam() {
+        let size = UVec3::new(25, 13, 10);
+        let per_axis = UVec3::new(4, 2, 2);
+        for rule in ["B1/S/M2", "B1/S/N3", "B1/S/BCC"] {
+            let rule: Rule = rule.parse().unwrap();
+            for at in [[0, 6, 6], [24, 6, 6], [1, 0, 0], [2, 12, 9], [12, 11, 8]] {
+                let new = || {
+                    let mut game = Game::new(size, 0., rule.clone(), 1., Boundary::Torus, 7, Seeding::Empty);
+                    let (cell, _) = Pattern::from_cells(std::iter::once((at, ALIVE)), None);
+                    game.stamp(cell, at).unwrap();
+                    game
+                };
+                let (mut active, mut full) = (new(), new());
+                // only the stamped cell's chunk changed - everywhere else back already equals grid
+                let [x, y, z] = at.map(|v| v as usize / CHUNK);
+                active.chunks.set_changed([linear_index(x, y, z, per_axis)]);
+                for _ in 0..12 {
+                    active.advance_state();
+                    full.chunks.mark_all();
+                    full.advance_state();
+                    assert!(active.grid == full.grid, "{} from {:?} at generation {}", rule, at, active.generation);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use bevy::prelude::UVec3;
+
+use crate::rule::{ALIVE, DEAD};
+
+// helper to compute linear index = x * h*d + y * d + z for a w x h x d block
+// shared with the pre-spawned cell entities so both line up
+#[inline]
+pub fn linear_index(x: usize, y: usize, z: usize, size: UVec3) -> usize {
+    (x * size.y as usize + y) * size.z as usize + z
+}
+
+// inverse of linear_index
+#[inline]
+pub fn coords(i: usize, size: UVec3) -> (usize, usize, usize) {
+    let (h, d) = (size.y as usize, size.z as usize);
+    (i / (h * d), (i / d) % h, i % d)
+}
+
+// number of cells in a block of the given size
+#[inline]
+pub fn volume(size: UVec3) -> usize {
+    size.x as usize * size.y as usize * size.z as usize
+}
+
+// Flat w x h x d block of cells - alive cells are packed 64 per word,
+// the full state byte is only kept around for Generations rules (more than 2 states)
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Grid {
+    size: UVec3,
+    alive: Vec<u64>,
+    states: Vec<u8>, // empty for 2 state rules - alive bit is the whole state then
+}
+
+impl Grid {
+    pub fn new(size: UVec3, states: u8) -> Grid {
+        let cells = volume(size);
+        Grid {
+            size,
+            alive: vec![0; cells.div_ceil(64)],
+            states: if states > 2 { vec![DEAD; cells] } else { Vec::new() },
+        }
+    }
+
+    // cells per axis
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    #[inline]
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        linear_index(x, y, z, self.size)
+    }
+
+    #[inline]
+    pub fn is_alive(&self, x: usize, y: usize, z: usize) -> bool {
+        let i = self.index(x, y, z);
+        self.alive[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    // DEAD, ALIVE or a dying state
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u8 {
+        if self.states.is_empty() {
+            if self.is_alive(x, y, z) { ALIVE } else { DEAD }
+        } else {
+            self.states[self.index(x, y, z)]
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: u8) {
+        let i = self.index(x, y, z);
+        if state == ALIVE {
+            self.alive[i / 64] |= 1 << (i % 64);
+        } else {
+            self.alive[i / 64] &= !(1 << (i % 64));
+        }
+        if !self.states.is_empty() {
+            self.states[i] = state;
+        }
+    }
+
+    // state of the cell at a linear index
+    #[inline]
+    pub fn get_at(&self, i: usize) -> u8 {
+        if self.states.is_empty() {
+            if self.alive[i / 64] & (1 << (i % 64)) != 0 { ALIVE } else { DEAD }
+        } else {
+            self.states[i]
+        }
+    }
+
+    pub fn set_at(&mut self, i: usize, state: u8) {
+        let (x, y, z) = coords(i, self.size);
+        self.set(x, y, z, state);
+    }
+
+    // linear indices of the cells whose state differs from the other grid's (of the same size)
+    pub fn diff<'a>(&'a self, other: &'a Grid) -> impl Iterator<Item = usize> + 'a {
+        (0..self.alive.len()).flat_map(move |w| {
+            let mut bits = if self.states.is_empty() {
+                self.alive[w] ^ other.alive[w]
+            } else {
+                let end = (w * 64 + 64).min(self.states.len());
+                (w * 64..end).filter(|&i| self.states[i] != other.states[i]).fold(0, |m, i| m | 1 << (i % 64))
+            };
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let i = w * 64 + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(i)
+            })
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.alive.fill(0);
+        self.states.fill(DEAD);
+    }
+
+    pub fn population(&self) -> usize {
+        self.alive.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    // nothing alive or dying
+    pub fn is_empty(&self) -> bool {
+        self.population() == 0 && self.states.iter().all(|&s| s == DEAD)
+    }
+
+    // hash of all cell states - equal grids hash equal
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.alive.hash(&mut hasher);
+        self.states.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // coordinates of all alive cells - skips empty words so sparse worlds are cheap
+    pub fn iter_alive(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        let n = self.size;
+        self.alive.iter().enumerate().flat_map(move |(w, &word)| {
+            let mut bits = word;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let i = w * 64 + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(coords(i, n))
+            })
+        })
+    }
+}
+
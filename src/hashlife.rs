@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use bevy::prelude::UVec3;
+
+use crate::game::Boundary;
+use crate::grid::{linear_index, Grid};
+use crate::neighborhood::Neighborhood;
+use crate::rule::{Rule, ALIVE, DEAD};
+
+type NodeId = u32;
+
+// index layouts - cells of a leaf, cells of a brute forced level 3 node, children, overlapping subcubes
+const LEAF: UVec3 = UVec3::splat(4);
+const BRUTE: UVec3 = UVec3::splat(8);
+const OCTANTS: UVec3 = UVec3::splat(2);
+const SUBCUBES: UVec3 = UVec3::splat(3);
+
+// past this many nodes the caches are thrown away after a jump and rebuilt on the next one
+const MAX_NODES: usize = 1 << 22;
+
+// Octree node - level l covers 2^l cells per dimension.
+// Children / octants are ordered like linear_index i.e. octant (x,y,z) => x*4 + y*2 + z
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Node {
+    Leaf(u64),              // level 2 - 4x4x4 cells, bit linear_index(x,y,z,4)
+    Inner(u8, [NodeId; 8]), // level, octants
+}
+
+// 3D HashLife - identical subcubes share one canonical node and the result of a node
+// (its center after some generations) is only ever computed once.
+// The world is treated as the torus tiling all of space, so it's exact for torus worlds
+// whose size is a power of two and only used for radius 1 moore, 2 state deterministic rules without side conditions
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    ids: HashMap<Node, NodeId>,
+    results: HashMap<(NodeId, usize), NodeId>,
+    empty: Vec<NodeId>, // canonical empty node per level, index = level - 2
+}
+
+impl HashLife {
+    pub fn new(rule: Rule) -> HashLife {
+        HashLife {
+            rule,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            results: HashMap::new(),
+            empty: Vec::new(),
+        }
+    }
+
+    pub fn supports(rule: &Rule, boundary: Boundary, size: UVec3) -> bool {
+        rule.states == 2
+            && !rule.is_stochastic()
+            && !rule.is_directional()
+            && rule.neighborhood == Neighborhood::Moore(1)
+            && boundary == Boundary::Torus
+            && size.x == size.y
+            && size.y == size.z
+            && size.x.is_power_of_two()
+            && size.x >= 8
+    }
+
+    // the grid after the given number of generations
+    pub fn advance(&mut self, grid: &Grid, generations: usize) -> Grid {
+        // supports() makes sure it's a cube
+        let n = grid.size().x as usize;
+        let level = n.trailing_zeros() as u8;
+        let mut torus = self.build(grid, [0, 0, 0], level);
+
+        let mut left = generations;
+        while left > 0 {
+            // the center of 8 copies of the torus is the torus shifted by n/2 -
+            // swapping opposite octants undoes the shift
+            let steps = left.min(n / 2);
+            let universe = self.intern(Node::Inner(level + 1, [torus; 8]));
+            let result = self.result(universe, steps);
+            let shifted = self.children(result);
+            torus = self.intern(Node::Inner(level, std::array::from_fn(|o| shifted[o ^ 7])));
+            left -= steps;
+        }
+
+        let mut out = Grid::new(grid.size(), 2);
+        self.flatten(torus, [0, 0, 0], &mut out);
+
+        if self.nodes.len() > MAX_NODES {
+            *self = HashLife::new(self.rule.clone());
+        }
+        out
+    }
+
+    fn intern(&mut self, node: Node) -> NodeId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.ids.insert(node, id);
+        id
+    }
+
+    fn level(&self, id: NodeId) -> u8 {
+        match self.nodes[id as usize] {
+            Node::Leaf(_) => 2,
+            Node::Inner(level, _) => level,
+        }
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 8] {
+        match self.nodes[id as usize] {
+            Node::Inner(_, children) => children,
+            Node::Leaf(_) => unreachable!("leaves have no child nodes"),
+        }
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= (level - 2) as usize {
+            let node = match self.empty.last() {
+                None => Node::Leaf(0),
+                Some(&below) => Node::Inner(self.empty.len() as u8 + 2, [below; 8]),
+            };
+            let id = self.intern(node);
+            self.empty.push(id);
+        }
+        self.empty[(level - 2) as usize]
+    }
+
+    fn build(&mut self, grid: &Grid, [x0, y0, z0]: [usize; 3], level: u8) -> NodeId {
+        if level == 2 {
+            let mut bits = 0;
+            for x in 0..4 {
+                for y in 0..4 {
+                    for z in 0..4 {
+                        if grid.is_alive(x0 + x, y0 + y, z0 + z) {
+                            bits |= 1 << linear_index(x, y, z, LEAF);
+                        }
+                    }
+                }
+            }
+            return self.intern(Node::Leaf(bits));
+        }
+
+        let half = 1 << (level - 1);
+        let mut children = [0; 8];
+        for (o, child) in children.iter_mut().enumerate() {
+            let origin = [x0 + (o >> 2 & 1) * half, y0 + (o >> 1 & 1) * half, z0 + (o & 1) * half];
+            *child = self.build(grid, origin, level - 1);
+        }
+        self.intern(Node::Inner(level, children))
+    }
+
+    fn flatten(&mut self, id: NodeId, [x0, y0, z0]: [usize; 3], grid: &mut Grid) {
+        let level = self.level(id);
+        if id == self.empty(level) {
+            return;
+        }
+        match self.nodes[id as usize] {
+            Node::Leaf(bits) => {
+                for x in 0..4 {
+                    for y in 0..4 {
+                        for z in 0..4 {
+                            if bits & (1 << linear_index(x, y, z, LEAF)) != 0 {
+                                grid.set(x0 + x, y0 + y, z0 + z, ALIVE);
+                            }
+                        }
+                    }
+                }
+            }
+            Node::Inner(level, children) => {
+                let half = 1 << (level - 1);
+                for (o, child) in children.into_iter().enumerate() {
+                    let origin = [x0 + (o >> 2 & 1) * half, y0 + (o >> 1 & 1) * half, z0 + (o & 1) * half];
+                    self.flatten(child, origin, grid);
+                }
+            }
+        }
+    }
+
+    // grandchild (i,j,k) of a node - i,j,k in 0..4
+    fn grandchild(&self, id: NodeId, i: usize, j: usize, k: usize) -> NodeId {
+        let child = self.children(id)[linear_index(i / 2, j / 2, k / 2, OCTANTS)];
+        self.children(child)[linear_index(i % 2, j % 2, k % 2, OCTANTS)]
+    }
+
+    // center half of a level l node after steps generations, 0 <= steps <= 2^(l-2)
+    fn result(&mut self, id: NodeId, steps: usize) -> NodeId {
+        if let Some(&res) = self.results.get(&(id, steps)) {
+            return res;
+        }
+
+        let level = self.level(id);
+        let res = if level == 3 {
+            self.brute_force(id, steps)
+        } else if steps == 0 {
+            let center = std::array::from_fn(|o| self.grandchild(id, 1 + (o >> 2 & 1), 1 + (o >> 1 & 1), 1 + (o & 1)));
+            self.intern(Node::Inner(level - 1, center))
+        } else {
+            // first half of the steps on the 27 overlapping subcubes of half size,
+            // the rest on the 8 subcubes put together from those results
+            let first = steps.min(1 << (level - 3));
+            let mut mid = [0; 27];
+            for a in 0..3 {
+                for b in 0..3 {
+                    for c in 0..3 {
+                        let sub = std::array::from_fn(|o| self.grandchild(id, a + (o >> 2 & 1), b + (o >> 1 & 1), c + (o & 1)));
+                        let sub = self.intern(Node::Inner(level - 1, sub));
+                        mid[linear_index(a, b, c, SUBCUBES)] = self.result(sub, first);
+                    }
+                }
+            }
+
+            let mut out = [0; 8];
+            for (o, slot) in out.iter_mut().enumerate() {
+                let (a, b, c) = (o >> 2 & 1, o >> 1 & 1, o & 1);
+                let sub = std::array::from_fn(|p| mid[linear_index(a + (p >> 2 & 1), b + (p >> 1 & 1), c + (p & 1), SUBCUBES)]);
+                let sub = self.intern(Node::Inner(level - 1, sub));
+                *slot = self.result(sub, steps - first);
+            }
+            self.intern(Node::Inner(level - 1, out))
+        };
+
+        self.results.insert((id, steps), res);
+        res
+    }
+
+    // level 3 (8x8x8) nodes are small enough to just run the rule on directly
+    fn brute_force(&mut self, id: NodeId, steps: usize) -> NodeId {
+        let mut cells = [false; 512];
+        for (o, leaf) in self.children(id).into_iter().enumerate() {
+            let Node::Leaf(bits) = self.nodes[leaf as usize] else {
+                unreachable!("level 3 nodes are made of leaves")
+            };
+            let (ox, oy, oz) = ((o >> 2 & 1) * 4, (o >> 1 & 1) * 4, (o & 1) * 4);
+            for x in 0..4 {
+                for y in 0..4 {
+                    for z in 0..4 {
+                        cells[linear_index(ox + x, oy + y, oz + z, BRUTE)] = bits & (1 << linear_index(x, y, z, LEAF)) != 0;
+                    }
+                }
+            }
+        }
+
+        // each generation the part that can still be computed shrinks by one cell per face
+        for step in 0..steps {
+            let mut next = cells;
+            for x in step + 1..7 - step {
+                for y in step + 1..7 - step {
+                    for z in step + 1..7 - step {
+                        let mut count = 0;
+                        for dx in x - 1..=x + 1 {
+                            for dy in y - 1..=y + 1 {
+                                for dz in z - 1..=z + 1 {
+                                    count += cells[linear_index(dx, dy, dz, BRUTE)] as usize;
+                                }
+                            }
+                        }
+                        let alive = cells[linear_index(x, y, z, BRUTE)];
+                        count -= alive as usize;
+                        let state = if alive { ALIVE } else { DEAD };
+                        next[linear_index(x, y, z, BRUTE)] = self.rule.next(state, count) == ALIVE;
+                    }
+                }
+            }
+            cells = next;
+        }
+
+        let mut bits = 0;
+        for x in 0..4 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    if cells[linear_index(x + 2, y + 2, z + 2, BRUTE)] {
+                        bits |= 1 << linear_index(x, y, z, LEAF);
+                    }
+                }
+            }
+        }
+        self.intern(Node::Leaf(bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::seeding::Seeding;
+
+    // a jump has to land exactly where stepping one generation at a time does
+    #[test]
+    fn jump_matches_stepping() {
+        for size in [16, 32] {
+            for (name, rule) in Rule::PRESETS.into_iter().filter(|(_, r)| HashLife::supports(r, Boundary::Torus, UVec3::splat(size))) {
+                let new = || Game::new(UVec3::splat(size), 0.3, rule.clone(), 1., Boundary::Torus, 42, Seeding::Random);
+                let (mut jumped, mut stepped) = (new(), new());
+                jumped.jump_to(37);
+                for _ in 0..37 {
+                    stepped.advance_state();
+                }
+                assert!(jumped.grid == stepped.grid, "{} on a {}^3 torus", name, size);
+            }
+        }
+    }
+}
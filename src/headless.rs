@@ -0,0 +1,273 @@
+use std::fmt;
+use std::io::Write;
+
+use bevy::prelude::UVec3;
+
+use crate::game::{Boundary, Game};
+use crate::lenia::{LeniaError, LeniaParams};
+use crate::pattern::{Pattern, PatternError};
+use crate::rule::{Rule, RuleError};
+use crate::seeding::Seeding;
+use crate::session::{Session, SessionError};
+use crate::stats::csv_header;
+
+pub const USAGE: &str = "\
+usage: game_of_life_bevy --headless [options]
+
+  --size N | X,Y,Z      cells per axis
+  --prob P              spawn probability of the random soup (0 to 1)
+  --rule RULE           e.g. \"B5,6/S5-7\", \"4-5/5/2/M\", \"B10-12/S8-14/M2\", \"B8-10/S6-12/W2,1,0\", \"B5-6D3-9/S4-7\", \"B4/S3-5/FCC\" or \"B5:0.5,6/S5-7/T0.001\"
+  --boundary NAME       Torus, Dead, Alive, Mirror or Unbounded
+  --seeding NAME        Random, Empty, Block, Shell, Sphere, Cross, Oscillator, Glider, Scattered,
+                        Cube soup, Ball soup (both optionally with a radius, e.g. \"Ball soup 12\"),
+                        Falloff, Noise, Mirror, Mirror 2 or Mirror 3
+  --lenia PARAMS        continuous Lenia world instead of a rule, e.g. \"radius=4,mu=0.15,sigma=0.025,dt=0.1\"
+  --seed N              seed of the soup - random if left out
+  --generations N       how many generations to run
+  --pattern FILE        .rle3 pattern stamped into the middle (its rule is used unless --rule is given)
+  --load FILE           carry on from a saved session instead - the options above are ignored then
+  --csv FILE            write the stats of every generation to FILE at the end
+  --out FILE            save the last generation as a .rle3 pattern
+  --until-settled       stop early once the world died out, froze or started cycling
+  --quiet               don't print a stats row per generation";
+
+// flags followed by a value
+const VALUE_FLAGS: [&str; 12] = [
+    "--size", "--prob", "--rule", "--boundary", "--seeding", "--lenia", "--seed",
+    "--generations", "--pattern", "--load", "--csv", "--out",
+];
+
+// A Game run without any window - `--headless` on the command line, e.g.
+//
+//   game_of_life_bevy --headless --size 32 --rule "B5/S4-5" --seed 42 --generations 500 --out last.rle3
+//
+// Prints the stats of every generation as CSV to stdout (same columns as 'C' in the window),
+// so it can be piped into whatever does the batch experiment / regression check
+#[derive(Clone, Debug)]
+pub struct Headless {
+    pub size: UVec3,
+    pub prob: f64,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub seeding: Seeding,
+    pub lenia: Option<LeniaParams>, // rule, boundary, seeding and prob aren't used then
+    pub seed: Option<u64>,
+    pub generations: usize,
+    pub pattern: Option<Pattern>,
+    pub session: Option<Session>,
+    pub csv: Option<String>,
+    pub out: Option<String>,
+    pub until_settled: bool,
+    pub quiet: bool,
+}
+
+// A command line that doesn't make sense - shared by --headless and --explore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgError {
+    UnknownFlag(String),
+    MissingValue(String),     // flag without anything after it
+    BadValue(String, String), // flag and the value it couldn't make sense of
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgError::UnknownFlag(flag) => write!(f, "unknown option {}", flag),
+            ArgError::MissingValue(flag) => write!(f, "{} needs a value", flag),
+            ArgError::BadValue(flag, value) => write!(f, "'{}' is not a valid value for {}", value, flag),
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadlessError {
+    Arg(ArgError),
+    BadRule(RuleError),
+    BadLenia(LeniaError),
+    Pattern(PatternError),
+    Session(SessionError),
+    Io(String),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::Arg(e) => write!(f, "{}", e),
+            HeadlessError::BadRule(e) => write!(f, "bad --rule: {}", e),
+            HeadlessError::BadLenia(e) => write!(f, "bad --lenia: {}", e),
+            HeadlessError::Pattern(e) => write!(f, "{}", e),
+            HeadlessError::Session(e) => write!(f, "{}", e),
+            HeadlessError::Io(e) => write!(f, "couldn't write output: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<ArgError> for HeadlessError {
+    fn from(e: ArgError) -> HeadlessError {
+        HeadlessError::Arg(e)
+    }
+}
+
+impl From<PatternError> for HeadlessError {
+    fn from(e: PatternError) -> HeadlessError {
+        HeadlessError::Pattern(e)
+    }
+}
+
+impl From<SessionError> for HeadlessError {
+    fn from(e: SessionError) -> HeadlessError {
+        HeadlessError::Session(e)
+    }
+}
+
+impl From<std::io::Error> for HeadlessError {
+    fn from(e: std::io::Error) -> HeadlessError {
+        HeadlessError::Io(e.to_string())
+    }
+}
+
+// "64" for a cube or "64,32,16" / "64x32x16"
+pub fn parse_size(s: &str) -> Option<UVec3> {
+    let dims: Vec<u32> = s.split([',', 'x']).map(|v| v.trim().parse().ok().filter(|&v| v > 0)).collect::<Option<_>>()?;
+    match dims[..] {
+        [n] => Some(UVec3::splat(n)),
+        [x, y, z] => Some(UVec3::new(x, y, z)),
+        _ => None,
+    }
+}
+
+// walks a command line - the flags in value_flags come with the argument after them, all others with None.
+// handle reports the flags it doesn't know itself
+pub fn parse_flags<E: From<ArgError>>(
+    args: &[String],
+    value_flags: &[&str],
+    mut handle: impl FnMut(&String, Option<&String>) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = if value_flags.contains(&flag.as_str()) {
+            Some(args.next().ok_or_else(|| ArgError::MissingValue(flag.clone()))?)
+        } else {
+            None
+        };
+        handle(flag, value)?;
+    }
+    Ok(())
+}
+
+// menu names, case doesn't matter
+pub fn find<T: Copy>(all: &[(&str, T)], name: &str) -> Option<T> {
+    all.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| *v)
+}
+
+impl Headless {
+    // starts out with the defaults the window uses
+    pub fn new(size: UVec3, prob: f64, rule: Rule, boundary: Boundary, seeding: Seeding) -> Headless {
+        Headless {
+            size,
+            prob,
+            rule,
+            boundary,
+            seeding,
+            lenia: None,
+            seed: None,
+            generations: 100,
+            pattern: None,
+            session: None,
+            csv: None,
+            out: None,
+            until_settled: false,
+            quiet: false,
+        }
+    }
+
+    // the command line without the program name - options the window knows (--rule, --seed, --pattern, --load, --csv) mean the same here
+    pub fn parse_args(mut self, args: &[String]) -> Result<Headless, HeadlessError> {
+        let mut rule_given = false;
+        parse_flags(args, &VALUE_FLAGS, |flag, value| {
+            let Some(value) = value else {
+                match flag.as_str() {
+                    "--headless" => {}
+                    "--until-settled" => self.until_settled = true,
+                    "--quiet" => self.quiet = true,
+                    _ => return Err(HeadlessError::Arg(ArgError::UnknownFlag(flag.clone()))),
+                }
+                return Ok(());
+            };
+
+            let bad = || HeadlessError::Arg(ArgError::BadValue(flag.clone(), value.clone()));
+            match flag.as_str() {
+                "--size" => self.size = parse_size(value).ok_or_else(bad)?,
+                "--prob" => self.prob = value.parse().ok().filter(|p| (0. ..=1.).contains(p)).ok_or_else(bad)?,
+                "--rule" => {
+                    self.rule = value.parse().map_err(HeadlessError::BadRule)?;
+                    rule_given = true;
+                }
+                "--boundary" => self.boundary = find(&Boundary::ALL, value).ok_or_else(bad)?,
+                "--seeding" => self.seeding = value.parse().map_err(|_| bad())?,
+                "--lenia" => self.lenia = Some(value.parse().map_err(HeadlessError::BadLenia)?),
+                "--seed" => self.seed = Some(value.parse().map_err(|_| bad())?),
+                "--generations" => self.generations = value.parse().map_err(|_| bad())?,
+                "--pattern" => self.pattern = Some(Pattern::load(value)?),
+                "--load" => self.session = Some(Session::load(value)?),
+                "--csv" => self.csv = Some(value.clone()),
+                "--out" => self.out = Some(value.clone()),
+                _ => unreachable!("{} is missing above", flag),
+            }
+            Ok(())
+        })?;
+
+        // like in the window a pattern's own rule is used unless one was given
+        if let Some(rule) = self.pattern.as_ref().and_then(|p| p.rule.clone()).filter(|_| !rule_given) {
+            self.rule = rule;
+        }
+        Ok(self)
+    }
+
+    // runs the generations, writing a stats row for each of them to out - returns the game as it ended up
+    pub fn run(self, out: &mut impl Write) -> Result<Game, HeadlessError> {
+        let mut game = match self.session {
+            Some(session) => session.into_game(),
+            None => {
+                let seed = self.seed.unwrap_or_else(Game::random_seed);
+                let mut game = match self.lenia {
+                    Some(params) => Game::new_lenia(self.size, params, 1., seed),
+                    None => Game::new(self.size, self.prob, self.rule, 1., self.boundary, seed, self.seeding),
+                };
+                if let Some(pattern) = self.pattern {
+                    let offset = game.centered(&pattern);
+                    game.stamp(pattern, offset)?;
+                }
+                game
+            }
+        };
+
+        if !self.quiet {
+            write!(out, "{}", csv_header(&game.stats_header()))?;
+            if let Some(row) = game.stats.last() {
+                writeln!(out, "{}", row.csv_row())?;
+            }
+        }
+
+        let end = game.generation + self.generations;
+        while game.generation < end && !(self.until_settled && game.settled.is_some()) {
+            game.advance_state();
+            if let Some(row) = game.stats.last().filter(|_| !self.quiet) {
+                writeln!(out, "{}", row.csv_row())?;
+            }
+        }
+        out.flush()?;
+
+        if let Some(path) = &self.csv {
+            std::fs::write(path, game.stats_csv())?;
+        }
+        if let Some(path) = &self.out {
+            game.capture().0.save(path)?;
+        }
+        Ok(game)
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+
+use crate::grid::Grid;
+use crate::rule::{ALIVE, DEAD};
+use crate::sparse::{SparseWorld, CHUNK_CELLS};
+
+// at most this many steps are kept - older ones get dropped first
+pub const MAX_STEPS: usize = 1000;
+// and at most this many stored cell states across all of them (~6 bytes each for diffs)
+pub const MAX_CELLS: usize = 1 << 23;
+
+// what changed between two generations
+#[derive(Clone, Debug)]
+pub enum Change {
+    // only the cells that differ - cell index as in Grid::index with its state before and after
+    Cells { index: Vec<u32>, before: Vec<u8>, after: Vec<u8> },
+    // unbounded worlds have no fixed indexing - the whole world before and after
+    World { before: SparseWorld, after: SparseWorld },
+}
+
+impl Change {
+    // between two grids of the same size
+    pub fn between(before: &Grid, after: &Grid) -> Change {
+        let index: Vec<u32> = before.diff(after).map(|i| i as u32).collect();
+        Change::Cells {
+            before: index.iter().map(|&i| before.get_at(i as usize)).collect(),
+            after: index.iter().map(|&i| after.get_at(i as usize)).collect(),
+            index,
+        }
+    }
+
+    // cells that became alive and cells that stopped being alive
+    pub fn births_deaths(&self) -> (usize, usize) {
+        let mut births = 0;
+        let mut deaths = 0;
+        let mut count = |before: u8, after: u8| {
+            births += (before != ALIVE && after == ALIVE) as usize;
+            deaths += (before == ALIVE && after != ALIVE) as usize;
+        };
+        match self {
+            Change::Cells { before, after, .. } => {
+                for (&b, &a) in before.iter().zip(after) {
+                    count(b, a);
+                }
+            }
+            Change::World { before, after } => {
+                for (pos, cells) in after.chunks() {
+                    match before.chunk(pos) {
+                        Some(old) => old.iter().zip(cells).for_each(|(&b, &a)| count(b, a)),
+                        None => cells.iter().for_each(|&a| count(DEAD, a)),
+                    }
+                }
+                // chunks that got dropped - everything in them died
+                for (_, cells) in before.chunks().filter(|(pos, _)| !after.contains_chunk(pos)) {
+                    cells.iter().for_each(|&b| count(b, DEAD));
+                }
+            }
+        }
+        (births, deaths)
+    }
+
+    // stored cell states - what the MAX_CELLS budget counts
+    fn cost(&self) -> usize {
+        match self {
+            Change::Cells { index, .. } => index.len(),
+            Change::World { before, after } => (before.chunk_count() + after.chunk_count()) * CHUNK_CELLS,
+        }
+    }
+}
+
+// one recorded step from generation `from` to generation `to` -
+// usually to = from + 1, jumps (see Game::jump_to) are recorded as a single step
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub from: usize,
+    pub to: usize,
+    pub change: Change,
+}
+
+// Bounded undo / redo of generations - past holds the steps that led to the current generation,
+// future the ones stepped back over (until the simulation runs on from there)
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    past: VecDeque<Step>,
+    future: Vec<Step>,
+    cells: usize, // total cost of past + future
+}
+
+impl History {
+    pub fn clear(&mut self) {
+        self.past.clear();
+        self.future.clear();
+        self.cells = 0;
+    }
+
+    // a newly computed step - whatever was stepped back over is no longer reachable
+    pub fn record(&mut self, step: Step) {
+        for old in self.future.drain(..) {
+            self.cells -= old.change.cost();
+        }
+        self.cells += step.change.cost();
+        self.past.push_back(step);
+        while self.past.len() > MAX_STEPS || (self.cells > MAX_CELLS && self.past.len() > 1) {
+            if let Some(old) = self.past.pop_front() {
+                self.cells -= old.change.cost();
+            }
+        }
+    }
+
+    // the step to undo to go back a generation
+    pub fn back(&mut self) -> Option<&Step> {
+        let step = self.past.pop_back()?;
+        self.future.push(step);
+        self.future.last()
+    }
+
+    // the step to redo to go forward again
+    pub fn forward(&mut self) -> Option<&Step> {
+        let step = self.future.pop()?;
+        self.past.push_back(step);
+        self.past.back()
+    }
+
+    // oldest and newest generation that can be reached without computing anything
+    pub fn range(&self, current: usize) -> (usize, usize) {
+        let oldest = self.past.front().map_or(current, |s| s.from);
+        let newest = self.future.first().map_or(current, |s| s.to);
+        (oldest, newest)
+    }
+}
@@ -0,0 +1,223 @@
+use bevy::prelude::UVec3;
+
+use crate::grid::Grid;
+use crate::pattern::Pattern;
+use crate::rule::DEAD;
+
+// How cells are packed in space. The non cubic ones live on the cubic grid as a subset of its cells -
+// face centered cubic (FCC) on the cells whose x+y+z is even, body centered cubic (BCC) on the ones
+// whose x, y and z are all even or all odd. Cells off the lattice always stay dead
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Lattice {
+    #[default]
+    Cubic,
+    Fcc, // 12 equidistant neighbors - cells are rhombic dodecahedra
+    Bcc, // 8 nearest + 6 second nearest neighbors - cells are truncated octahedra
+}
+
+impl Lattice {
+    pub const ALL: [(&'static str, Lattice); 3] = [("Cubic", Lattice::Cubic), ("FCC", Lattice::Fcc), ("BCC", Lattice::Bcc)];
+
+    #[inline]
+    pub fn contains(&self, [x, y, z]: [i64; 3]) -> bool {
+        match self {
+            Lattice::Cubic => true,
+            Lattice::Fcc => (x + y + z).rem_euclid(2) == 0,
+            Lattice::Bcc => x.rem_euclid(2) == y.rem_euclid(2) && y.rem_euclid(2) == z.rem_euclid(2),
+        }
+    }
+
+    // size rounded up to even sides where a torus needs it - the parity only holds across the seam
+    // if it doesn't change there, otherwise the cells along it would lose neighbors to the masked ones
+    pub fn fit(&self, size: UVec3, wrap: bool) -> UVec3 {
+        if *self == Lattice::Cubic || !wrap {
+            return size;
+        }
+        size + size % 2
+    }
+
+    // the cells sharing a face with a cell - 6, 12 or 14 of them
+    pub fn offsets(&self) -> Vec<[isize; 3]> {
+        let mut offsets = Vec::new();
+        for dz in -2isize..=2 {
+            for dy in -2isize..=2 {
+                for dx in -2isize..=2 {
+                    let d = [dx, dy, dz];
+                    let moved = d.iter().filter(|&&v| v != 0).count();
+                    let far = d.iter().filter(|&&v| v.abs() == 2).count();
+                    let face = match self {
+                        Lattice::Cubic => moved == 1 && far == 0,
+                        Lattice::Fcc => moved == 2 && far == 0,
+                        Lattice::Bcc => (moved == 3 && far == 0) || (moved == 1 && far == 1),
+                    };
+                    if face {
+                        offsets.push(d);
+                    }
+                }
+            }
+        }
+        offsets
+    }
+
+    // corners of the cell around (0,0,0) - the points halfway between it and its neighbors' shared corners
+    fn corners(&self) -> Vec<[f32; 3]> {
+        let mut corners = Vec::new();
+        let half = [-0.5, 0.5];
+        match self {
+            Lattice::Cubic => {
+                for x in half {
+                    for y in half {
+                        for z in half {
+                            corners.push([x, y, z]);
+                        }
+                    }
+                }
+            }
+            Lattice::Fcc => {
+                // the tips of the octahedron and the corners of the cube the dodecahedron is built from
+                for a in 0..3 {
+                    for v in [-1., 1.] {
+                        let mut c = [0.; 3];
+                        c[a] = v;
+                        corners.push(c);
+                    }
+                }
+                corners.extend(Lattice::Cubic.corners());
+            }
+            Lattice::Bcc => {
+                // every permutation of (0, ±1/2, ±1)
+                for a in 0..3 {
+                    for b in (0..3).filter(|&b| b != a) {
+                        for u in half {
+                            for v in [-1., 1.] {
+                                let mut c = [0.; 3];
+                                c[a] = u;
+                                c[b] = v;
+                                corners.push(c);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        corners
+    }
+
+    // faces of the cell around (0,0,0) in the grid's units - one per neighbor offset, each with its corners
+    // ordered counter clockwise seen from outside. The cells of neighbors touch without gaps
+    pub fn faces(&self) -> Vec<Vec<[f32; 3]>> {
+        let corners = self.corners();
+        let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let sub = |a: [f32; 3], b: [f32; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+        let cross = |a: [f32; 3], b: [f32; 3]| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+
+        self.offsets()
+            .into_iter()
+            .map(|d| {
+                // the face lies on the plane halfway to the neighbor
+                let n = d.map(|v| v as f32);
+                let plane = dot(n, n) / 2.;
+                let mut face: Vec<[f32; 3]> = corners.iter().copied().filter(|&c| (dot(c, n) - plane).abs() < 1e-4).collect();
+
+                let k = face.len() as f32;
+                let center = face.iter().fold([0.; 3], |s, c| [s[0] + c[0] / k, s[1] + c[1] / k, s[2] + c[2] / k]);
+                let u = sub(face[0], center);
+                let w = cross(n, u);
+                let angle = |c: [f32; 3]| dot(sub(c, center), w).atan2(dot(sub(c, center), u));
+                face.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+                face
+            })
+            .collect()
+    }
+
+    // sets every cell off the lattice dead
+    pub fn mask(&self, grid: &mut Grid) {
+        if *self == Lattice::Cubic {
+            return;
+        }
+        let size = grid.size();
+        for x in 0..size.x as usize {
+            for y in 0..size.y as usize {
+                for z in 0..size.z as usize {
+                    if !self.contains([x as i64, y as i64, z as i64]) {
+                        grid.set(x, y, z, DEAD);
+                    }
+                }
+            }
+        }
+    }
+
+    // the pattern with the cells that would end up off the lattice at offset left out
+    pub fn restrict(&self, pattern: &Pattern, offset: [i64; 3]) -> Pattern {
+        let mut pattern = pattern.clone();
+        if *self == Lattice::Cubic {
+            return pattern;
+        }
+        let size = pattern.size;
+        let mut i = 0;
+        for x in 0..size.x as i64 {
+            for y in 0..size.y as i64 {
+                for z in 0..size.z as i64 {
+                    if !self.contains([offset[0] + x, offset[1] + y, offset[2] + z]) {
+                        pattern.cells[i] = DEAD;
+                    }
+                    i += 1;
+                }
+            }
+        }
+        pattern
+    }
+
+    // offset moved by at most one cell per axis so as many of the pattern's cells as possible land on the lattice -
+    // a pattern saved from a non cubic world keeps all of them
+    pub fn align(&self, pattern: &Pattern, offset: [i64; 3]) -> [i64; 3] {
+        if *self == Lattice::Cubic {
+            return offset;
+        }
+        let size = pattern.size;
+        let mut alive = Vec::new();
+        let mut i = 0;
+        for x in 0..size.x as i64 {
+            for y in 0..size.y as i64 {
+                for z in 0..size.z as i64 {
+                    if pattern.cells[i] != DEAD {
+                        alive.push([x, y, z]);
+                    }
+                    i += 1;
+                }
+            }
+        }
+
+        let shifts = (0..8).map(|s| [offset[0] + (s & 1), offset[1] + (s >> 1 & 1), offset[2] + (s >> 2 & 1)]);
+        let on = |o: [i64; 3]| alive.iter().filter(|p| self.contains([o[0] + p[0], o[1] + p[1], o[2] + p[2]])).count();
+        // first one wins ties - no shift at all if nothing lands off the lattice
+        shifts.fold((offset, on(offset)), |best, o| if on(o) > best.1 { (o, on(o)) } else { best }).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // across the seam of a torus every cell on the lattice still has all its neighbors on it
+    #[test]
+    fn torus_keeps_every_neighbor() {
+        for lattice in [Lattice::Fcc, Lattice::Bcc] {
+            let size = lattice.fit(UVec3::new(7, 9, 4), true).to_array().map(|v| v as i64);
+            assert!(size.iter().all(|v| v % 2 == 0));
+            for x in 0..size[0] {
+                for y in 0..size[1] {
+                    for z in 0..size[2] {
+                        if !lattice.contains([x, y, z]) {
+                            continue;
+                        }
+                        let p = [x, y, z];
+                        let wrapped = |d: &[isize; 3]| std::array::from_fn(|a| (p[a] + d[a] as i64).rem_euclid(size[a]));
+                        let on = lattice.offsets().iter().filter(|d| lattice.contains(wrapped(d))).count();
+                        assert_eq!(on, lattice.offsets().len(), "{:?} at {:?}", lattice, [x, y, z]);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::UVec3;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::grid::{linear_index, volume, Grid};
+use crate::rule::{ALIVE, DEAD};
+
+// cells at or above this value count as alive - for the stats, patterns and what gets shown
+pub const THRESHOLD: f32 = 0.1;
+
+// Parameters of a Lenia world - "radius=4,mu=0.15,sigma=0.025,dt=0.1", missing keys keep their default
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LeniaParams {
+    pub radius: u8, // of the kernel in cells
+    pub mu: f32,    // neighborhood sum the growth function peaks at
+    pub sigma: f32, // width of the growth peak
+    pub dt: f32,    // step size - a cell changes by at most dt per generation
+}
+
+impl Default for LeniaParams {
+    fn default() -> LeniaParams {
+        LeniaParams { radius: 4, mu: 0.15, sigma: 0.025, dt: 0.1 }
+    }
+}
+
+impl LeniaParams {
+    // -1 far away from mu, 1 right at it
+    #[inline]
+    fn growth(&self, u: f32) -> f32 {
+        let d = (u - self.mu) / self.sigma;
+        2. * (-d * d / 2.).exp() - 1.
+    }
+}
+
+impl fmt::Display for LeniaParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "radius={},mu={},sigma={},dt={}", self.radius, self.mu, self.sigma, self.dt)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeniaError(pub String); // the part it couldn't make sense of
+
+impl fmt::Display for LeniaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' is not a Lenia parameter (radius=2-15, mu, sigma and dt above 0)", self.0)
+    }
+}
+
+impl std::error::Error for LeniaError {}
+
+impl FromStr for LeniaParams {
+    type Err = LeniaError;
+
+    fn from_str(s: &str) -> Result<LeniaParams, LeniaError> {
+        let mut params = LeniaParams::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let bad = || LeniaError(item.to_string());
+            let (key, value) = item.split_once('=').ok_or_else(bad)?;
+            let value = value.trim();
+            let positive = |v: &str| v.parse::<f32>().ok().filter(|v| v.is_finite() && *v > 0.).ok_or_else(bad);
+            match key.trim() {
+                // a radius of 1 leaves the kernel without any cell - nothing but decay
+                "radius" => params.radius = value.parse().ok().filter(|r| (2..=15).contains(r)).ok_or_else(bad)?,
+                "mu" => params.mu = positive(value)?,
+                "sigma" => params.sigma = positive(value)?,
+                "dt" => params.dt = positive(value)?,
+                _ => return Err(bad()),
+            }
+        }
+        Ok(params)
+    }
+}
+
+// Continuous cellular automaton - every cell holds a value in 0..=1 instead of a state.
+// Each generation a smooth ring shaped kernel is convolved over the values around a cell,
+// the growth function turns that weighted sum into -1..1 and the cell moves dt times that, clamped to 0..=1.
+// The world always wraps around like a torus
+#[derive(Clone, Debug)]
+pub struct Lenia {
+    pub params: LeniaParams,
+    pub size: UVec3,
+    pub cells: Vec<f32>, // in linear_index order
+    back: Vec<f32>,
+    kernel: Vec<([isize; 3], f32)>, // offsets within the radius with their weight - weights add up to 1
+}
+
+impl Lenia {
+    pub fn new(params: LeniaParams, size: UVec3) -> Lenia {
+        Lenia {
+            params,
+            size,
+            cells: vec![0.; volume(size)],
+            back: vec![0.; volume(size)],
+            kernel: kernel(params.radius),
+        }
+    }
+
+    // random values in a ball around the center - a couple of kernel radii across
+    pub fn soup(params: LeniaParams, size: UVec3, seed: u64) -> Lenia {
+        let mut lenia = Lenia::new(params, size);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mid = size.to_array().map(|v| v as f32 / 2.);
+        let radius = (2. * params.radius as f32).min(size.min_element() as f32 / 2.);
+        for x in 0..size.x as usize {
+            for y in 0..size.y as usize {
+                for z in 0..size.z as usize {
+                    let d2: f32 = [x, y, z].iter().zip(mid).map(|(&v, m)| (v as f32 + 0.5 - m).powi(2)).sum();
+                    if d2 <= radius * radius {
+                        lenia.set(x, y, z, rng.r#gen::<f32>());
+                    }
+                }
+            }
+        }
+        lenia
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.cells[linear_index(x, y, z, self.size)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: f32) {
+        self.cells[linear_index(x, y, z, self.size)] = value.clamp(0., 1.);
+    }
+
+    // nothing left - stays that way as growth is negative for an empty neighborhood
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|&v| v == 0.)
+    }
+
+    // sum of all values
+    pub fn mass(&self) -> f32 {
+        self.cells.iter().sum()
+    }
+
+    // the cells at or above THRESHOLD as ALIVE, everything else DEAD
+    pub fn threshold(&self, grid: &mut Grid) {
+        for (i, &v) in self.cells.iter().enumerate() {
+            grid.set_at(i, if v >= THRESHOLD { ALIVE } else { DEAD });
+        }
+    }
+
+    pub fn step(&mut self) {
+        // every cell only depends on the previous values - one task per x slab
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let this = &*self;
+        let slabs = pool.scope(|s| {
+            for x in 0..this.size.x as usize {
+                s.spawn(async move { (x, this.step_slab(x)) });
+            }
+        });
+
+        let slab = (self.size.y * self.size.z) as usize;
+        for (x, values) in slabs {
+            self.back[x * slab..(x + 1) * slab].copy_from_slice(&values);
+        }
+        std::mem::swap(&mut self.cells, &mut self.back);
+    }
+
+    // next values of all cells with the given x, in linear_index order
+    fn step_slab(&self, x: usize) -> Vec<f32> {
+        let size = self.size.to_array().map(|v| v as isize);
+        let wrap = |v: isize, axis: usize| v.rem_euclid(size[axis]) as usize;
+        let mut next = Vec::with_capacity((size[1] * size[2]) as usize);
+        for y in 0..size[1] {
+            for z in 0..size[2] {
+                let u: f32 = self
+                    .kernel
+                    .iter()
+                    .map(|&([dx, dy, dz], w)| w * self.get(wrap(x as isize + dx, 0), wrap(y + dy, 1), wrap(z + dz, 2)))
+                    .sum();
+                let value = self.get(x, y as usize, z as usize) + self.params.dt * self.params.growth(u);
+                next.push(value.clamp(0., 1.));
+            }
+        }
+        next
+    }
+}
+
+// weights of the offsets within radius - a bump exp(4 - 1/(r(1-r))) over the distance r (0..1 of the radius),
+// so it's 0 at the cell itself and at the edge and peaks halfway
+fn kernel(radius: u8) -> Vec<([isize; 3], f32)> {
+    let r = radius as isize;
+    let mut kernel = Vec::new();
+    for dx in -r..=r {
+        for dy in -r..=r {
+            for dz in -r..=r {
+                let d = ((dx * dx + dy * dy + dz * dz) as f32).sqrt() / radius as f32;
+                if d > 0. && d < 1. {
+                    kernel.push(([dx, dy, dz], (4. - 1. / (d * (1. - d))).exp()));
+                }
+            }
+        }
+    }
+    let total: f32 = kernel.iter().map(|(_, w)| w).sum();
+    kernel.iter_mut().for_each(|(_, w)| *w /= total);
+    kernel
+}
@@ -1,25 +1,68 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use bevy::{
+    asset::RenderAssetUsages,
+    ecs::system::SystemParam,
     color::palettes::{css::WHITE, tailwind::{ GRAY_200, PURPLE_300, PURPLE_500, PURPLE_600}}, 
     input::{common_conditions::input_just_released, mouse::AccumulatedMouseMotion},
     prelude::*, 
+    render::mesh::PrimitiveTopology,
     window::{CursorGrabMode, PrimaryWindow, WindowFocused},
-    ui::{AlignItems, JustifyContent, FlexDirection, UiRect, Val}
+    ui::{AlignItems, JustifyContent, FlexDirection, RelativeCursorPosition, UiRect, Val}
 };
 
+pub mod chunks;
+pub mod detect;
+pub mod explore;
 pub mod game;
-
-use game::Game;
-use game::RuleSet;
+pub mod grid;
+pub mod hashlife;
+pub mod headless;
+pub mod history;
+pub mod lattice;
+pub mod lenia;
+pub mod neighborhood;
+pub mod pattern;
+pub mod rule;
+pub mod seeding;
+pub mod session;
+pub mod sparse;
+pub mod stats;
+
+use detect::Settled;
+use explore::Explorer;
+use game::{Boundary, Game};
+use headless::Headless;
+use lattice::Lattice;
+use lenia::LeniaParams;
+use pattern::Pattern;
+use grid::{linear_index, volume};
+use rule::{Rule, ALIVE, DEAD};
+use seeding::Seeding;
+use session::Session;
+use sparse::{ChunkPos, SparseWorld};
 
 // for simple experimenting bind all to easily findable constants - default values
 const PROB: f64 = 0.05;
-const SIZE: usize = 64; // above 64 laggy
-const RULE: RuleSet = RuleSet::Sparse;
+const SIZE: UVec3 = UVec3::splat(64); // cells per axis - above 64^3 laggy - mostly rendering, one cube entity per cell
+const RULE: Rule = Rule::SPARSE;
 const SPEED:f32 = 1.;
+const BOUNDARY: Boundary = Boundary::Dead;
+const SEEDING: Seeding = Seeding::Random;
+const JUMP: usize = 1000; // generations skipped with 'J' - instant on a torus with a power of two cube SIZE
+const ON_SETTLE: OnSettle = OnSettle::Nothing; // what happens once a soup died out / froze / started cycling - 'T' to switch
+const LENIA_SIZE: UVec3 = UVec3::splat(48); // Lenia convolves a whole ball per cell - smaller than SIZE to keep it running smoothly
 
 fn main() {
+    // no window at all - see headless.rs
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--headless") {
+        std::process::exit(run_headless(&args));
+    }
+    if args.iter().any(|a| a == "--explore") {
+        std::process::exit(run_explore(&args));
+    }
+
     let mut app = App::new();
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
@@ -31,11 +74,34 @@ fn main() {
 
     app.init_state::<AppState>();
     app.insert_resource(SelectedRule(RULE));
+    app.insert_resource(SelectedBoundary(BOUNDARY));
+    app.insert_resource(SelectedSeeding(seeding_from_args().unwrap_or(SEEDING)));
+    let pattern = pattern_from_args();
+    // a pattern's own rule shows up as the custom rule unless one was given
+    app.insert_resource(CustomRule(rule_from_args().or_else(|| pattern.as_ref().and_then(|p| p.rule.clone()))));
+    app.insert_resource(LoadedPattern(pattern));
+    app.insert_resource(ExploredRules(explored_from_args()));
+    app.insert_resource(lenia_from_args());
+    app.insert_resource(SelectedSeed(seed_from_args().unwrap_or_else(Game::random_seed)));
+    app.insert_resource(ON_SETTLE);
+    app.insert_resource(CsvPath(csv_path_from_args()));
+    let session = session_from_args();
+    if session.is_some() {
+        app.insert_state(AppState::InGame);
+    }
+    app.insert_resource(LoadedSession(session));
+    app.add_event::<Settled>();
     
     // menu tweak
     app.add_systems(OnEnter(AppState::Menu), setup_menu);
     app.add_systems(Update, ( 
         rule_buttons_interactions.run_if(in_state(AppState::Menu)),
+        lenia_button_interactions.run_if(in_state(AppState::Menu)),
+        choice_buttons_interactions::<SelectedBoundary>.run_if(in_state(AppState::Menu)),
+        choice_buttons_interactions::<SelectedSeeding>.run_if(in_state(AppState::Menu)),
+        seed_button_interactions.run_if(in_state(AppState::Menu)),
+        seed_typing.run_if(in_state(AppState::Menu)),
+        update_seed_text.run_if(in_state(AppState::Menu)),
     ));
 
     // despawn menu camera/UI when leaving Menu
@@ -44,6 +110,7 @@ fn main() {
     // when entering the game spawn game camera etc
     app.add_systems(OnEnter(AppState::InGame), (
         setup_simulation_state_counter,
+        setup_timeline,
         setup_pause,
         spawn_camera,
         spawn_all_cells.after(setup_game),
@@ -56,26 +123,83 @@ fn main() {
     app.add_systems(OnExit(AppState::InGame), (
         despawn_game_camera, 
         despawn_simulation_state,
-        despawn_lighting
+        despawn_timeline,
+        despawn_lighting,
+        despawn_chunk_cells
     ));
 
     app.add_systems(Update, (
         update_simulation_state_counter,
+        update_timeline,
+        timeline_scrub,
         camera_look,
         camera_move.after(camera_look),
         focus_events,
         toggle_grab.run_if(input_just_released(KeyCode::Escape)),
         place_cubes,
         game_step,
-        key_input_listener
+        key_input_listener,
+        quick_slots,
+        send_settled.after(game_step).after(key_input_listener),
+        on_settled.after(send_settled),
     ).run_if(in_state(AppState::InGame)));
 
     app.init_resource::<CubeData>();
+    app.init_resource::<ChunkEntities>();
     app.insert_resource(ClearColor(Color::srgb(0.82353, 0.66667, 0.94902))); //210., 170., 242.
     app.add_observer(apply_grab);
     app.run();
 }
 
+// exit code - 2 for a bad command line, 1 when writing the results failed
+fn run_headless(args: &[String]) -> i32 {
+    let headless = match Headless::new(SIZE, PROB, RULE, BOUNDARY, SEEDING).parse_args(args) {
+        Ok(headless) => headless,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, headless::USAGE);
+            return 2;
+        }
+    };
+    match headless.run(&mut std::io::stdout().lock()) {
+        Ok(game) => {
+            let population = game.stats.last().map_or(0, |row| row.population);
+            match game.settled {
+                Some(settled) => eprintln!("stopped at generation {} with {} alive - {}", game.generation, population, settled),
+                None => eprintln!("stopped at generation {} with {} alive", game.generation, population),
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// ranks rules without a window - exit codes like run_headless
+fn run_explore(args: &[String]) -> i32 {
+    let explorer = match Explorer::new(Game::random_seed()).parse_args(args) {
+        Ok(explorer) => explorer,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, explore::USAGE);
+            return 2;
+        }
+    };
+    let ranked = explorer.explore(|i, n, ranked| eprintln!("[{}/{}] {}", i, n, ranked));
+
+    let path = explorer.report_path.clone().unwrap_or_else(|| format!("rules_{}.csv", explorer.seed));
+    if let Err(e) = std::fs::write(&path, explorer.report(&ranked)) {
+        eprintln!("couldn't write {}: {}", path, e);
+        return 1;
+    }
+    println!("best of {} rules:", ranked.len());
+    for ranked in ranked.iter().take(10) {
+        println!("  {}", ranked);
+    }
+    println!("wrote {} - open the viewer with --rules {} to try the best {}", path, path, explore::TOP);
+    0
+}
+
 // Defined Game states
 #[derive(States, Clone, Eq, PartialEq, Debug, Hash, Default)]
 enum AppState {
@@ -104,14 +228,25 @@ fn update_simulation_state_counter(
     game: Res<Game>,
     mut query: Query<&mut Text, With<InGameText>>,
     timer: Res<StepTimer>,
+    on_settle: Res<OnSettle>,
 ) {
     // skip if generation hasn't advanced
-    if !game.is_changed(){
+    if !game.is_changed() && !on_settle.is_changed() {
         return; 
     }
 
     for mut text in &mut query {
-        *text = Text::new(format!("\n  Generation: {}\n  Speed: {:.2}", game.generation,1./ timer.0.duration().as_secs_f32()));
+        let rule = match (&game.lenia, game.rule.preset_name()) {
+            (Some(lenia), _) => format!("Lenia ({})", lenia.params),
+            (None, Some(name)) => format!("{} ({})", name, game.rule),
+            (None, None) => game.rule.to_string(),
+        };
+        let (oldest, newest) = game.history_range();
+        let settled = game.settled.map_or("no".to_string(), |s| s.to_string());
+        let (population, births, deaths) = game.stats.at(game.generation).map_or((0, 0, 0), |s| (s.population, s.births, s.deaths));
+        // Lenia's population only counts cells above the threshold - its mass sums every value
+        let mass = game.lenia.as_ref().map_or(String::new(), |lenia| format!(", mass {:.1}", lenia.mass()));
+        *text = Text::new(format!("\n  Generation: {}\n  Speed: {:.2}\n  Rule: {}\n  Boundary: {}\n  Start: {}\n  Seed: {}\n  Active chunks: {}/{}\n  History: {}-{}\n  Population: {} (+{} -{}){}\n  Settled: {}\n  On settle: {}", game.generation,1./ timer.0.duration().as_secs_f32(), rule, game.boundary.name(), game.seeding, game.seed, game.active_chunks, game.total_chunks(), oldest, newest, population, births, deaths, mass, settled, on_settle.name()));
     }
 }
 
@@ -122,6 +257,84 @@ fn despawn_simulation_state(mut commands: Commands,query: Query<Entity, With<InG
 
 }
 
+// Timeline - bar along the bottom covering the retained history, click/drag it to scrub (unfocus with Esc first)
+#[derive(Component)]
+struct Timeline;
+
+// filled part of the bar - up to the current generation
+#[derive(Component)]
+struct TimelineFill;
+
+fn setup_timeline(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.0),
+                left: Val::Percent(10.0),
+                width: Val::Percent(80.0),
+                height: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(PURPLE_600.into()),
+            Button,
+            RelativeCursorPosition::default(),
+            Timeline,
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor(PURPLE_300.into()),
+                TimelineFill,
+            ));
+        });
+}
+
+fn update_timeline(game: Res<Game>, mut fills: Query<&mut Node, With<TimelineFill>>) {
+    if !game.is_changed() {
+        return;
+    }
+
+    let (oldest, newest) = game.history_range();
+    let filled = if newest > oldest {
+        (game.generation - oldest) as f32 / (newest - oldest) as f32
+    } else {
+        1.
+    };
+    for mut node in &mut fills {
+        node.width = Val::Percent(filled * 100.);
+    }
+}
+
+// while the bar is held down the simulation pauses and follows the cursor through the history
+fn timeline_scrub(
+    bars: Query<(&Interaction, &RelativeCursorPosition), With<Timeline>>,
+    mut game: ResMut<Game>,
+    mut paused: ResMut<Paused>,
+) {
+    for (interaction, cursor) in &bars {
+        let (Interaction::Pressed, Some(pos)) = (interaction, cursor.normalized) else {
+            continue;
+        };
+        paused.0 = true;
+        let (oldest, newest) = game.history_range();
+        let target = oldest + (pos.x.clamp(0., 1.) * (newest - oldest) as f32).round() as usize;
+        if target != game.generation {
+            game.scrub_to(target);
+        }
+    }
+}
+
+fn despawn_timeline(mut commands: Commands, query: Query<Entity, With<Timeline>>) {
+    for bar in query.iter() {
+        commands.entity(bar).despawn();
+    }
+}
+
 // Cube spawning logic and pipeline of placing, generating cubes and setting all their properties 
 // Now using visibility flags instead of re/de - spawning each cube per tick
 
@@ -137,71 +350,240 @@ struct CubeCell {
 #[derive(Resource)]
 struct CellEntities {
     entities: Vec<Entity>,
-    size: usize,
+    colors: Vec<usize>, // color index into CubeData per cell, same indexing as entities
+    size: UVec3,
+    lattice: Lattice, // shape of the cells
+}
+
+impl CellEntities {
+    // made for the game's world - a loaded session can need another size or cell shape
+    fn fits(&self, game: &Game) -> bool {
+        self.size == game.size && self.lattice == game.rule.neighborhood.lattice()
+    }
 }
 
 // Initial render of the cubes - more expensive in gen. 0 - never run after 
 fn spawn_all_cells(
     mut commands: Commands,
     cube_data: Res<CubeData>,
+    game: Res<Game>,
     maybe_cells: Option<Res<CellEntities>>, // look if it already exists
 ) {
     // If already spawned  - e.g. re-entering => skip
-    if maybe_cells.is_some() {
-        return;
+    if let Some(cells) = maybe_cells {
+        if cells.fits(&game) {
+            return;
+        }
+        // a loaded session of another size or lattice - replace the whole block
+        despawn_cells(&mut commands, &cells);
     }
 
-    let mut entities: Vec<Entity> = Vec::with_capacity(SIZE*SIZE*SIZE);
+    let cells = spawn_cells(&mut commands, &cube_data, game.size, game.rule.neighborhood.lattice());
+    commands.insert_resource(cells);
+}
+
+fn spawn_cells(commands: &mut Commands, cube_data: &CubeData, size: UVec3, lattice: Lattice) -> CellEntities {
+    let mut entities: Vec<Entity> = Vec::with_capacity(volume(size));
+    let mut colors: Vec<usize> = Vec::with_capacity(volume(size));
 
-    for x in 0..SIZE {
-        for y in 0..SIZE {
-            for z in 0..SIZE {
+    for x in 0..size.x as usize {
+        for y in 0..size.y as usize {
+            for z in 0..size.z as usize {
                 let pos = Vec3::new(x as f32, y as f32, -(z as f32));
+                let color = cube_data.color();
                 let ent = commands
                     .spawn((
                         Transform::from_translation(pos),
-                        Mesh3d(cube_data.mesh()),
-                        MeshMaterial3d(cube_data.material()),
+                        Mesh3d(cube_data.mesh(lattice)),
+                        MeshMaterial3d(cube_data.material(color, 0)),
                         LifeCube,
                         CubeCell { x, y, z },
                         Visibility::Hidden,
                     ))
                     .id();
                 entities.push(ent);
+                colors.push(color);
             }
         }
     }
 
-    commands.insert_resource(CellEntities {
+    CellEntities {
         entities,
-        size: SIZE,
+        colors,
+        size,
+        lattice,
+    }
+}
+
+fn despawn_cells(commands: &mut Commands, cells: &CellEntities) {
+    for &ent in &cells.entities {
+        commands.entity(ent).despawn();
+    }
+}
+
+// show alive cells, fade dying ones (Generations rules) and hide dead ones
+fn sync_cells(commands: &mut Commands, game: &Game, cells: &CellEntities, chunk_cells: &mut ChunkEntities, cube_data: &CubeData) {
+    if let Some(world) = &game.sparse {
+        // the fixed block isn't used for an unbounded world - make sure it's hidden on the first display
+        if game.first_disp {
+            for &ent in &cells.entities {
+                commands.entity(ent).insert(Visibility::Hidden);
+            }
+        }
+        sync_chunks(commands, world, &game.rule, chunk_cells, cube_data);
+        return;
+    }
+    // a loaded session of another size or lattice - the block gets respawned before it's shown
+    if !cells.fits(game) {
+        return;
+    }
+
+    let n = cells.size;
+    if let Some(lenia) = &game.lenia {
+        for (idx, &value) in lenia.cells.iter().enumerate() {
+            show_value(commands, cells.entities[idx], cells.colors[idx], value, cube_data);
+        }
+        return;
+    }
+    for x in 0..n.x as usize {
+        for y in 0..n.y as usize {
+            for z in 0..n.z as usize {
+                let idx = linear_index(x, y, z, n);
+                show_cell(commands, cells.entities[idx], cells.colors[idx], game.grid.get(x, y, z), game.rule.states, cube_data);
+            }
+        }
+    }
+}
+
+fn show_cell(commands: &mut Commands, ent: Entity, color: usize, state: u8, states: u8, cube_data: &CubeData) {
+    if state == DEAD {
+        commands.entity(ent).insert(Visibility::Hidden);
+    } else {
+        let fade = fade_level(state, states);
+        commands.entity(ent).insert((
+            Visibility::Visible,
+            MeshMaterial3d(cube_data.material(color, fade)),
+        ));
+    }
+}
+
+// Lenia cells - hidden below the threshold, the lower the value the more faded
+fn show_value(commands: &mut Commands, ent: Entity, color: usize, value: f32, cube_data: &CubeData) {
+    if value < lenia::THRESHOLD {
+        commands.entity(ent).insert(Visibility::Hidden);
+    } else {
+        let fade = ((1. - value) / (1. - lenia::THRESHOLD) * (FADE_LEVELS + 1) as f32) as usize;
+        commands.entity(ent).insert((
+            Visibility::Visible,
+            MeshMaterial3d(cube_data.material(color, fade.min(FADE_LEVELS))),
+        ));
+    }
+}
+
+// Unbounded worlds - a block of cubes per live chunk instead of the fixed SIZE one
+#[derive(Resource, Default)]
+struct ChunkEntities {
+    chunks: HashMap<ChunkPos, CellEntities>,
+}
+
+// spawns cubes for chunks the world grew into, despawns the ones of freed chunks and syncs the rest
+fn sync_chunks(commands: &mut Commands, world: &SparseWorld, rule: &Rule, chunk_cells: &mut ChunkEntities, cube_data: &CubeData) {
+    chunk_cells.chunks.retain(|pos, block| {
+        let live = world.contains_chunk(pos);
+        if !live {
+            for &ent in &block.entities {
+                commands.entity(ent).despawn();
+            }
+        }
+        live
     });
 
+    for (pos, cells) in world.chunks() {
+        let block = chunk_cells.chunks.entry(*pos).or_insert_with(|| spawn_chunk(commands, cube_data, *pos, rule.neighborhood.lattice()));
+        for (idx, &state) in cells.iter().enumerate() {
+            show_cell(commands, block.entities[idx], block.colors[idx], state, rule.states, cube_data);
+        }
+    }
+}
+
+fn spawn_chunk(commands: &mut Commands, cube_data: &CubeData, pos: ChunkPos, lattice: Lattice) -> CellEntities {
+    let n = chunks::CHUNK;
+    let mut entities: Vec<Entity> = Vec::with_capacity(sparse::CHUNK_CELLS);
+    let mut colors: Vec<usize> = Vec::with_capacity(sparse::CHUNK_CELLS);
+    let origin = pos.map(|p| p as f32 * n as f32);
+
+    for x in 0..n {
+        for y in 0..n {
+            for z in 0..n {
+                let pos = Vec3::new(origin[0] + x as f32, origin[1] + y as f32, -(origin[2] + z as f32));
+                let color = cube_data.color();
+                let ent = commands
+                    .spawn((
+                        Transform::from_translation(pos),
+                        Mesh3d(cube_data.mesh(lattice)),
+                        MeshMaterial3d(cube_data.material(color, 0)),
+                        LifeCube,
+                        Visibility::Hidden,
+                    ))
+                    .id();
+                entities.push(ent);
+                colors.push(color);
+            }
+        }
+    }
+
+    CellEntities {
+        entities,
+        colors,
+        size: sparse::CHUNK_SIZE,
+        lattice,
+    }
+}
+
+fn despawn_chunk_cells(mut commands: Commands, mut chunk_cells: ResMut<ChunkEntities>) {
+    clear_chunks(&mut commands, &mut chunk_cells);
 }
 
-// helper to compute linear index = x * n*n + y * n + z
-#[inline]
-fn linear_index(x: usize, y: usize, z: usize, n: usize) -> usize {
-    x * n * n + y * n + z
+fn clear_chunks(commands: &mut Commands, chunk_cells: &mut ChunkEntities) {
+    for (_, block) in chunk_cells.chunks.drain() {
+        for ent in block.entities {
+            commands.entity(ent).despawn();
+        }
+    }
 }
 
+// maps a cell state onto 0 (alive) or 1..=FADE_LEVELS (dying, more faded the closer to dead)
+fn fade_level(state: u8, states: u8) -> usize {
+    if state <= ALIVE || states <= 2 {
+        return 0;
+    }
+    (1 + (state - 2) as usize * FADE_LEVELS / (states - 2) as usize).min(FADE_LEVELS)
+}
+
+// number of increasingly transparent variants per color used for dying cells
+const FADE_LEVELS: usize = 4;
+
 // generator for both reusing resource for spawning the cubes
 // assigns semi-random color to it and is made in a resuable way
 #[derive(Resource)]
 struct CubeData {
-    mesh: Handle<Mesh>,
-    materials: Vec<Handle<StandardMaterial>>,
+    meshes: [Handle<Mesh>; 3], // per lattice in Lattice::ALL order
+    materials: Vec<Vec<Handle<StandardMaterial>>>, // [color][fade level] - level 0 is the opaque alive color
     rng: std::sync::Mutex<rand::rngs::StdRng>,
 }
 
 impl CubeData {
-    fn mesh(&self) -> Handle<Mesh> {
-        self.mesh.clone()
+    fn mesh(&self, lattice: Lattice) -> Handle<Mesh> {
+        self.meshes[lattice as usize].clone()
     }
-    fn material(&self) -> Handle<StandardMaterial> {
-        use rand::seq::SliceRandom;
+    // random color index for a new cell
+    fn color(&self) -> usize {
+        use rand::Rng;
         let mut rng = self.rng.lock().unwrap();
-        self.materials.choose(&mut *rng).unwrap().clone()
+        rng.gen_range(0..self.materials.len())
+    }
+    fn material(&self, color: usize, fade: usize) -> Handle<StandardMaterial> {
+        self.materials[color][fade].clone()
     }
 }
 
@@ -209,25 +591,59 @@ impl FromWorld for CubeData {
     fn from_world(world: &mut World) -> Self {
         // makes the cubes spawn with a random color
         use rand::SeedableRng;
-        let mesh = world.resource_mut::<Assets<Mesh>>().add(Cuboid::from_length(1.)); 
+        let meshes = {
+            let mut mesh_assets = world.resource_mut::<Assets<Mesh>>();
+            Lattice::ALL.map(|(_, lattice)| mesh_assets.add(cell_mesh(lattice)))
+        };
         let mut materials = Vec::new();
         let mut material_assets = world.resource_mut::<Assets<StandardMaterial>>();
         for i in 0..36 {
             let color = Color::hsl((i * 10) as f32, 1., 0.5);
-            materials.push(material_assets.add(StandardMaterial {
+            let mut levels = vec![material_assets.add(StandardMaterial {
                 base_color: color,
                 ..Default::default()
-            }));
+            })];
+            // dying cells - fade out towards transparent
+            for level in 1..=FADE_LEVELS {
+                let alpha = 1. - level as f32 / (FADE_LEVELS + 1) as f32;
+                levels.push(material_assets.add(StandardMaterial {
+                    base_color: color.with_alpha(alpha),
+                    alpha_mode: AlphaMode::Blend,
+                    ..Default::default()
+                }));
+            }
+            materials.push(levels);
         }
         let seed = *b"GameOfLifeRandomSimulationColor1";
         CubeData {
-            mesh,
+            meshes,
             materials,
             rng: std::sync::Mutex::new(rand::rngs::StdRng::from_seed(seed)),
         }
     }
 }
 
+// cubes - or rhombic dodecahedra / truncated octahedra on the other lattices (see lattice.rs),
+// flat shaded so every face gets its own corners
+fn cell_mesh(lattice: Lattice) -> Mesh {
+    if lattice == Lattice::Cubic {
+        return Cuboid::from_length(1.).into();
+    }
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    for face in lattice.faces() {
+        // faces lie square to the direction of the neighbor behind them, which their center points along
+        let normal = face.iter().map(|&c| Vec3::from(c)).sum::<Vec3>().normalize();
+        for i in 1..face.len() - 1 {
+            positions.extend([face[0], face[i], face[i + 1]]);
+            normals.extend([normal.to_array(); 3]);
+        }
+    }
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+}
+
 // used as "tagging" so it can be despawned later
 #[derive(Component)]
 struct LifeCube;
@@ -238,76 +654,59 @@ fn place_cubes(
     keys: Res<ButtonInput<KeyCode>>,
     mut observer: Single<&mut Transform, With<Observer>>,
     cell_entities: Option<Res<CellEntities>>,
+    mut chunk_cells: ResMut<ChunkEntities>,
+    cube_data: Res<CubeData>,
 ){
 
     // intial render after which game_step function takes over
     if game.first_disp {
-        if let Some(cells) = cell_entities.as_ref() {
-            let n = cells.size;
-            for x in 0..n {
-                for y in 0..n {
-                    for z in 0..n {
-                        // new update system to toggle visibility - i.e. intial render is heavier as it generates
-                        // SIZE^3 cubes but subsequent ticks/generations are cheaper as they're only flag triggers
-
-                        if game.grid[x][y][z] {
-                            let idx = linear_index(x, y, z, n);
-                            let ent = cells.entities[idx];
-                            // show alive cells
-                            commands.entity(ent).insert(Visibility::Visible);
-                        } else {
-                            // ensure dead cells are hidden - they were created hidden but safe to set
-                            let idx = linear_index(x, y, z, n);
-                            let ent = cells.entities[idx];
-                            commands.entity(ent).insert(Visibility::Hidden);
-                        }
-                    }
-                }
-            }
+        if let Some(cells) = cell_entities.as_ref().filter(|c| c.fits(&game) || game.sparse.is_some()) {
+            // new update system to toggle visibility - i.e. intial render is heavier as it generates
+            // SIZE.x*SIZE.y*SIZE.z cubes but subsequent ticks/generations are cheaper as they're only flag triggers
+            sync_cells(&mut commands, &game, cells, &mut chunk_cells, &cube_data);
 
             game.first_disp = false;
         } 
 
     }
 
-    // reset game
-    if keys.just_pressed(KeyCode::KeyR) {
-        let mid = game.grid.len() as f32 /2.;
-        game.reset();
-        **observer = Transform::from_xyz(mid, mid, 3.0 * mid)
-        .looking_at(Vec3::new(mid, mid, mid), Vec3::Y);
+    // reset game - 'R' replays the same soup, 'N' rolls a new one
+    if keys.just_pressed(KeyCode::KeyR) || keys.just_pressed(KeyCode::KeyN) {
+        if keys.just_pressed(KeyCode::KeyN) {
+            game.reseed(Game::random_seed());
+        } else {
+            game.reset();
+        }
+        **observer = camera_home(game.size);
 
 
         // hide/show entities according to the reset state
         if let Some(cells) = cell_entities.as_ref() {
-            let n = cells.size;
-            for x in 0..n {
-                for y in 0..n {
-                    for z in 0..n {
-                        let idx = linear_index(x, y, z, n);
-                        let ent = cells.entities[idx];
-                        if game.grid[x][y][z] {
-                            commands.entity(ent).insert(Visibility::Visible);
-                        } else {
-                            commands.entity(ent).insert(Visibility::Hidden);
-                        }
-                    }
-                }
-            }
+            sync_cells(&mut commands, &game, cells, &mut chunk_cells, &cube_data);
         }
 
     }
 
 }
 
+// how the simulation runs - paused or not, how fast and what happens once it settles
+#[derive(SystemParam)]
+struct Playback<'w> {
+    paused: ResMut<'w, Paused>,
+    timer: ResMut<'w, StepTimer>,
+    on_settle: ResMut<'w, OnSettle>,
+}
+
 // Keyinputs to manipulate during simulation
 fn key_input_listener(   
     mut game: ResMut<Game>,
     keys: Res<ButtonInput<KeyCode>>,
-    mut paused: ResMut<Paused>,
-    mut timer: ResMut<StepTimer>,
+    mut playback: Playback,
+    mut seed: ResMut<SelectedSeed>,
+    csv: Res<CsvPath>,
     mut next_state: ResMut<NextState<AppState>>
 ) {
+    let Playback { paused, timer, on_settle } = &mut playback;
     
     // listen for pause
     if keys.just_pressed(KeyCode::Space) {
@@ -327,8 +726,48 @@ fn key_input_listener(
         timer.0.set_duration(Duration::from_secs_f32(new_duration));
     }
 
+    // step through the history one generation at a time while paused
+    if paused.0 && keys.just_pressed(KeyCode::Comma) {
+        game.step_back();
+    }
+
+    if paused.0 && keys.just_pressed(KeyCode::Period) {
+        game.step_forward();
+    }
+
+    // fast forward
+    if keys.just_pressed(KeyCode::KeyJ) {
+        let target = game.generation + JUMP;
+        game.jump_to(target);
+    }
+
+    // switch what happens once the soup settles
+    if keys.just_pressed(KeyCode::KeyT) {
+        **on_settle = on_settle.next();
+    }
+
+    // dump the stats of every generation so far
+    if keys.just_pressed(KeyCode::KeyC) {
+        let path = csv.0.clone().unwrap_or_else(|| format!("stats_{}.csv", game.seed));
+        match std::fs::write(&path, game.stats_csv()) {
+            Ok(()) => println!("wrote stats of {} generations to {}", game.stats.series().len(), path),
+            Err(e) => eprintln!("couldn't write {}: {}", path, e),
+        }
+    }
+
+    // save what's alive right now as a pattern file
+    if keys.just_pressed(KeyCode::KeyP) {
+        let path = format!("pattern_{}_{}.rle3", game.seed, game.generation);
+        match game.capture().0.save(&path) {
+            Ok(()) => println!("saved pattern to {}", path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
     // go back to menu
     if keys.just_pressed(KeyCode::KeyQ) { 
+        // keep the seed so the same soup can be replayed under another rule
+        seed.0 = game.seed;
         game.generation = 0;
         game.reset();
         next_state.set(AppState::Menu);
@@ -336,25 +775,84 @@ fn key_input_listener(
 }
 
 
+// Quick save slots - '1'..'5' loads a slot, Shift + '1'..'5' saves the whole session into it
+const SLOTS: [KeyCode; 5] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5];
+
+fn slot_path(slot: usize) -> String {
+    format!("saves/slot_{}.gol3d", slot)
+}
+
+fn quick_slots(
+    mut game: ResMut<Game>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut observer: Single<&mut Transform, With<Observer>>,
+    mut timer: ResMut<StepTimer>,
+    mut cubes: Cubes,
+) {
+    let Some(slot) = SLOTS.iter().position(|&k| keys.just_pressed(k)).map(|i| i + 1) else {
+        return;
+    };
+    let path = slot_path(slot);
+
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        // a session only knows about states - the values would be lost
+        if game.lenia.is_some() {
+            eprintln!("Lenia worlds can't be saved as sessions - 'P' saves the cells above the threshold as a pattern");
+            return;
+        }
+        // game.speed is only what it started with - the arrow keys change the timer
+        let mut session = Session::of(&game);
+        session.speed = timer.0.duration().as_secs_f32();
+        match session.save(&path) {
+            Ok(()) => println!("saved generation {} to {}", game.generation, path),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    let session = match Session::load(&path) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("couldn't load slot {}: {}", slot, e);
+            return;
+        }
+    };
+    *game = session.into_game();
+
+    // the block of cubes is made for one size and lattice - a session of another one needs a new block
+    if let Some(cells) = cubes.cells.as_ref().filter(|c| !c.fits(&game)) {
+        despawn_cells(&mut cubes.commands, cells);
+        let cells = spawn_cells(&mut cubes.commands, &cubes.data, game.size, game.rule.neighborhood.lattice());
+        cubes.commands.insert_resource(cells);
+    }
+    clear_chunks(&mut cubes.commands, &mut cubes.chunks);
+    **observer = camera_home(game.size);
+    timer.0.set_duration(Duration::from_secs_f32(game.speed));
+    // first_disp is set - place_cubes shows the loaded world
+    println!("loaded generation {} from {}", game.generation, path);
+}
+
 // Observer/Camera logic, positioning, spawning, despawning
 #[derive(Component)]
 struct Observer;
 
 fn spawn_camera(mut commands: Commands, game: Res<Game>) {
 
-    // have camera look roughly at middle of cube structure
-    // and be positioned infront of center of cube (with some distance)
-
-    let mid = game.grid.len() as f32 /2.;
-    
     commands.spawn((
         Camera3d::default(),
-        Transform::from_xyz(mid, mid, 3.*mid)  
-            .looking_at(Vec3::from_array([mid,mid,mid]), Vec3::Y), 
+        camera_home(game.size),
         Observer,
     ));
 }
 
+// have camera look roughly at middle of cube structure
+// and be positioned infront of center of cube (with some distance) - distance from the largest side
+fn camera_home(size: UVec3) -> Transform {
+    let mid = size.as_vec3() / 2.;
+    Transform::from_xyz(mid.x, mid.y, 3. * mid.max_element())
+        .looking_at(mid, Vec3::Y)
+}
+
 fn despawn_game_camera(mut commands: Commands, cams: Query<Entity, With<Observer>>) {
     for cam in cams.iter() {
         commands.entity(cam).despawn();
@@ -399,12 +897,13 @@ fn camera_move(
     inputs: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
     paused: Res<Paused>,
+    game: Res<Game>,
 ) {
     if !paused.0 {
         return; // early exit if NOT paused
     }
 
-    let cam_speed = SIZE as f32 / 3.;
+    let cam_speed = game.size.max_element() as f32 / 3.;
     let mut delta = Vec3::ZERO;
     if inputs.pressed(KeyCode::KeyA) {
         delta.x -= cam_speed;
@@ -472,20 +971,126 @@ fn setup_pause(mut commands: Commands) {
     commands.insert_resource(Paused(false));
 }
 
+// Settling - the game notices extinction, still lifes and cycles itself (see detect.rs),
+// here it's turned into a Settled event and optionally acted upon
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+enum OnSettle {
+    Nothing, // keep running
+    Pause,
+    Reset,   // start over with a new seed
+}
+
+impl OnSettle {
+    fn name(&self) -> &'static str {
+        match self {
+            OnSettle::Nothing => "keep running",
+            OnSettle::Pause => "pause",
+            OnSettle::Reset => "reset with new seed",
+        }
+    }
+
+    fn next(&self) -> OnSettle {
+        match self {
+            OnSettle::Nothing => OnSettle::Pause,
+            OnSettle::Pause => OnSettle::Reset,
+            OnSettle::Reset => OnSettle::Nothing,
+        }
+    }
+}
+
+// sends the event whenever the game settles - however it got there (stepping, jumping, history)
+fn send_settled(game: Res<Game>, mut last: Local<Option<Settled>>, mut events: EventWriter<Settled>) {
+    if game.settled == *last {
+        return;
+    }
+    *last = game.settled;
+    if let Some(settled) = game.settled {
+        events.write(settled);
+    }
+}
+
+fn on_settled(
+    mut events: EventReader<Settled>,
+    on_settle: Res<OnSettle>,
+    mut game: ResMut<Game>,
+    mut paused: ResMut<Paused>,
+) {
+    // only the latest one matters
+    if events.read().last().is_none() {
+        return;
+    }
+    match *on_settle {
+        OnSettle::Nothing => {}
+        OnSettle::Pause => paused.0 = true,
+        // a start the seed doesn't change would just settle again right away - e.g. Empty every frame
+        OnSettle::Reset if game.lenia.is_none() && !game.seeding.uses_seed() => paused.0 = true,
+        OnSettle::Reset => game.reseed(Game::random_seed()),
+    }
+}
+
 // Game Transition
 
-fn setup_game(mut commands: Commands, selected: Res<SelectedRule>) {
-    let game = Game::new(SIZE, PROB, selected.0, 1. / SPEED);
+// everything picked in the menu - what setup_game builds the world from
+#[derive(SystemParam)]
+struct MenuChoices<'w> {
+    rule: Res<'w, SelectedRule>,
+    boundary: Res<'w, SelectedBoundary>,
+    seeding: Res<'w, SelectedSeeding>,
+    seed: Res<'w, SelectedSeed>,
+    lenia: Res<'w, LeniaMode>,
+}
+
+fn setup_game(
+    mut commands: Commands,
+    choices: MenuChoices,
+    pattern: Res<LoadedPattern>,
+    mut session: ResMut<LoadedSession>,
+) {
+    // a session from --load replaces everything picked in the menu - only the first time round
+    if let Some(session) = session.0.take() {
+        commands.insert_resource(session.into_game());
+        return;
+    }
+
+    let seed = choices.seed.0;
+    let mut game = if choices.lenia.selected {
+        Game::new_lenia(LENIA_SIZE, choices.lenia.params, 1. / SPEED, seed)
+    } else {
+        Game::new(SIZE, PROB, choices.rule.0.clone(), 1. / SPEED, choices.boundary.0, seed, choices.seeding.0)
+    };
+    if let Some(pattern) = &pattern.0 {
+        let offset = game.centered(pattern);
+        if let Err(e) = game.stamp(pattern.clone(), offset) {
+            eprintln!("ignoring --pattern: {}", e);
+        }
+    }
     commands.insert_resource(game);
 }
 
+// the cube entities and what they're made of - everything sync_cells needs besides the game
+#[derive(SystemParam)]
+struct Cubes<'w, 's> {
+    commands: Commands<'w, 's>,
+    cells: Option<Res<'w, CellEntities>>,
+    chunks: ResMut<'w, ChunkEntities>,
+    data: Res<'w, CubeData>,
+}
+
+impl Cubes<'_, '_> {
+    // shows the game's cells on the pre-spawned entities - nothing until those are there
+    fn sync(&mut self, game: &Game) {
+        if let Some(cells) = self.cells.as_ref() {
+            sync_cells(&mut self.commands, game, cells, &mut self.chunks, &self.data);
+        }
+    }
+}
+
 fn game_step(
     time: Res<Time>,
     mut timer: ResMut<StepTimer>,
     mut game: ResMut<Game>,
-    mut commands: Commands,
     paused: Res<Paused>,
-    cell_entities: Option<Res<CellEntities>>
+    mut cubes: Cubes,
 ) {
 
     if paused.0 {
@@ -500,22 +1105,7 @@ fn game_step(
             game.advance_state();
 
             // update visuals using pre-spawned entities - just flag switch
-            if let Some(cells) = cell_entities.as_ref() {
-                let n = cells.size;
-                for x in 0..n {
-                    for y in 0..n {
-                        for z in 0..n {
-                            let idx = linear_index(x, y, z, n);
-                            let ent = cells.entities[idx];
-                            if game.grid[x][y][z] {
-                                commands.entity(ent).insert(Visibility::Visible);
-                            } else {
-                                commands.entity(ent).insert(Visibility::Hidden);
-                            }
-                        }
-                    }
-                }
-            } 
+            cubes.sync(&game);
         
         }
 
@@ -561,7 +1151,9 @@ struct MenuUI;
 
 fn setup_menu(
     mut commands: Commands, 
-    selected: Option<Res<SelectedRule>>
+    custom: Res<CustomRule>,
+    explored: Res<ExploredRules>,
+    choices: MenuChoices,
 ) {
     commands.spawn((Camera2d::default(), MenuCamera));
 
@@ -591,7 +1183,7 @@ fn setup_menu(
                 TextColor(WHITE.into()),
                 
             ));
-                let current = selected.map(|r| r.0).unwrap_or(RULE);
+                let current = choices.rule.0.clone();
                 parent
                     .spawn(Node {
                         margin: UiRect::top(Val::Px(10.0)),
@@ -604,17 +1196,18 @@ fn setup_menu(
                         ..default()
                     })
                     .with_children(|row| {
-                        let options = [
-                            (RuleSet::Balanced, "Balanced"),
-                            (RuleSet::Dense, "Dense"),
-                            (RuleSet::Sparse, "Sparse"),
-                            (RuleSet::Chaotic, "Chaotic"),
-                            (RuleSet::NoDeath, "No Death"),
-                        ];
-    
-                        for (rule_variant, label) in options {
+                        let mut options: Vec<(String, Rule)> = Rule::PRESETS.iter().map(|(name, rule)| (name.to_string(), rule.clone())).collect();
+                        if let Some(rule) = &custom.0 {
+                            options.push(("Custom".to_string(), rule.clone()));
+                        }
+                        // best rules of an explorer report - by rank
+                        for (rank, rule) in explored.0.iter().enumerate() {
+                            options.push((format!("#{}", rank + 1), rule.clone()));
+                        }
+
+                        for (label, rule_variant) in options {
                             // choose initial background depending on selected
-                            let bg = if rule_variant == current {
+                            let bg = if rule_variant == current && !choices.lenia.selected {
                                 PURPLE_300.into()
                             } else {
                                 PURPLE_600.into()
@@ -640,10 +1233,168 @@ fn setup_menu(
                                 ));
                             });
                         }
+
+                        // continuous world instead of a rule - see lenia.rs
+                        let bg = if choices.lenia.selected { PURPLE_300.into() } else { PURPLE_600.into() };
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::all(Val::Px(8.0)),
+                                ..default()
+                            },
+                            BackgroundColor(bg),
+                            LeniaButton,
+                        ))
+                        .with_children(|btn| {
+                            btn.spawn((
+                                Text::new("Lenia"),
+                                TextFont {
+                                    font_size: 30.0,
+                                    ..default()
+                                },
+                                TextColor(WHITE.into()),
+                            ));
+                        });
+                    });
+
+            // boundary only gets selected here - the game starts once a mode is chosen
+            parent.spawn((
+                Text::new("Boundary"),
+                TextFont {
+                    font_size: 25.0,
+                    ..default()
+                },
+                TextColor(WHITE.into()),
+            ));
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (label, boundary_variant) in Boundary::ALL {
+                        let bg = if boundary_variant == choices.boundary.0 {
+                            PURPLE_300.into()
+                        } else {
+                            PURPLE_600.into()
+                        };
+
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::all(Val::Px(8.0)),
+                                ..default()
+                            },
+                            BackgroundColor(bg),
+                            ChoiceButton(SelectedBoundary(boundary_variant)),
+                        ))
+                        .with_children(|btn| {
+                            btn.spawn((
+                                Text::new(label),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(WHITE.into()),
+                            ));
+                        });
+                    }
+                });
+
+            // starting structure - picked like the boundary
+            parent.spawn((
+                Text::new("Start"),
+                TextFont {
+                    font_size: 25.0,
+                    ..default()
+                },
+                TextColor(WHITE.into()),
+            ));
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.0),
+                    row_gap: Val::Px(8.0),
+                    flex_wrap: FlexWrap::Wrap,
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (label, seeding_variant) in Seeding::ALL {
+                        let bg = if seeding_variant.name() == choices.seeding.0.name() {
+                            PURPLE_300.into()
+                        } else {
+                            PURPLE_600.into()
+                        };
+
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::all(Val::Px(8.0)),
+                                ..default()
+                            },
+                            BackgroundColor(bg),
+                            ChoiceButton(SelectedSeeding(seeding_variant)),
+                        ))
+                        .with_children(|btn| {
+                            btn.spawn((
+                                Text::new(label),
+                                TextFont {
+                                    font_size: 20.0,
+                                    ..default()
+                                },
+                                TextColor(WHITE.into()),
+                            ));
+                        });
+                    }
+                });
+
+            // seed of the soup - typed in with the number keys or rolled with the button
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        Text::new(format!("Seed: {}", choices.seed.0)),
+                        TextFont {
+                            font_size: 25.0,
+                            ..default()
+                        },
+                        TextColor(WHITE.into()),
+                        SeedText,
+                    ));
+                    row.spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                        BackgroundColor(PURPLE_600.into()),
+                        SeedButton,
+                    ))
+                    .with_children(|btn| {
+                        btn.spawn((
+                            Text::new("New seed"),
+                            TextFont {
+                                font_size: 20.0,
+                                ..default()
+                            },
+                            TextColor(WHITE.into()),
+                        ));
                     });
+                });
 
             parent.spawn((
-                Text::new(format!("Will start automatically after choosing Mode\nSpawn probability at {}, \nSize per dimension {}\nTick Speed per Generation at {}\nAll configurable by altering constants at the top of the main.rs file\n\nPress: \n'Esc' to (un)focus\n'R' to reset\n'<-' '->' use arrow keys to adjust speed\n'Q' to go back to menu\n'Space' to pause simulation\nWhen Paused - can move around with WASD keys", {PROB}, {SIZE}, {SPEED})),
+                Text::new(format!("Will start automatically after choosing Mode - pick the Boundary and Start first\nOscillator is made for Balanced/Dense, Glider and Scattered for Bays\nSpawn probability at {}, \nSize {}x{}x{} cells\nTick Speed per Generation at {}\nAll configurable by altering constants at the top of the main.rs file\nCustom rules can be passed with --rule \"B5,6/S5-7\" (add \"/C5\" for dying states, \"/W2,1,0\" to weigh face/edge/corner neighbors)\nand made directional by what lies below (D), beside (L) and above (U): --rule \"B5-6D3-9/S4-7\" grows upwards\nor put on a face / body centered cubic lattice with \"/FCC\" (12 neighbors) or \"/BCC\" (14 neighbors) - a torus gets even sides for those\nor made stochastic with chances per count and a temperature: --rule \"B5:0.5,6/S5-7:0.9/T0.001\"\nRank random rules without a window with --explore, then show the best with --rules rules_<seed>.csv\nLenia is continuous on a {}x{}x{} torus, tune it with --lenia \"radius=4,mu=0.15,sigma=0.025,dt=0.1\"\nStart soups can be bounded, thinned out, clumped by noise or mirrored - pass a radius with --seeding \"Ball soup 12\"\nType digits to set the seed (Backspace/Delete to remove) or pass --seed 1234\n\nPress: \n'Esc' to (un)focus\n'R' to reset with the same seed\n'N' to reset with a new seed\n'<-' '->' use arrow keys to adjust speed\n'J' to jump {} generations ahead\n'Q' to go back to menu\n'Space' to pause simulation\n',' '.' to step back/forward a generation while paused\n'T' to switch between keep running/pause/reset once the soup settles\n'C' to save per generation stats as CSV (path with --csv stats.csv)\n'P' to save the live cells as a .rle3 pattern, load one with --pattern file.rle3\nDrag the timeline at the bottom to scrub through past generations (unfocus first)\n'Shift' + '1'-'5' to save the session into a slot, '1'-'5' to load it again (or pass --load saves/slot_1.gol3d)\nWhen Paused - can move around with WASD keys", {PROB}, SIZE.x, SIZE.y, SIZE.z, LENIA_SIZE.x, LENIA_SIZE.y, LENIA_SIZE.z, {SPEED}, {JUMP})),
                 TextFont {
                     font_size: 20.0,
                     ..default()
@@ -653,12 +1404,167 @@ fn setup_menu(
         });
 }
 
+#[derive(Resource, Clone, Debug)]
+struct SelectedRule(pub Rule);
+
+// rule given on the command line - e.g. `--rule "B5,6/S5-7"`, `--rule "4-5/5/2/M"`, `--rule "4/4/5/M"`
+// or with another neighborhood `--rule "B10-12/S8-14/M2"`, `--rule "B2/S1-3/N"`
+// or weighted face/edge/corner neighbors `--rule "B8-10/S6-12/W2,1,0"`
+// or directional with conditions on the neighbors below (D), beside (L) and above (U) `--rule "B5-6D3-9/S4-7"`
+// or on another lattice `--rule "B4/S3-5/FCC"`, `--rule "B4-5/S3-6/BCC"`
+#[derive(Resource, Clone, Debug)]
+struct CustomRule(Option<Rule>);
+
+fn rule_from_args() -> Option<Rule> {
+    let args: Vec<String> = std::env::args().collect();
+    let pos = args.iter().position(|a| a == "--rule")?;
+    match args.get(pos + 1)?.parse() {
+        Ok(rule) => Some(rule),
+        Err(e) => {
+            eprintln!("ignoring --rule: {}", e);
+            None
+        }
+    }
+}
+
+// seed the soup is generated from - `--seed 1234` on the command line, random otherwise
+#[derive(Resource, Clone, Copy, Debug)]
+struct SelectedSeed(pub u64);
+
+fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    let pos = args.iter().position(|a| a == "--seed")?;
+    match args.get(pos + 1)?.parse() {
+        Ok(seed) => Some(seed),
+        Err(e) => {
+            eprintln!("ignoring --seed: {}", e);
+            None
+        }
+    }
+}
+
+// what the world starts out as - `--seeding "Ball soup 12"` preselects it in the menu
+fn seeding_from_args() -> Option<Seeding> {
+    let args: Vec<String> = std::env::args().collect();
+    let pos = args.iter().position(|a| a == "--seeding")?;
+    let value = args.get(pos + 1)?;
+    match value.parse() {
+        Ok(seeding) => Some(seeding),
+        Err(_) => {
+            eprintln!("ignoring --seeding: '{}' is not a start", value);
+            None
+        }
+    }
+}
+
+// where 'C' writes the stats CSV to - `--csv stats.csv`, stats_<seed>.csv otherwise
+#[derive(Resource, Clone, Debug)]
+struct CsvPath(Option<String>);
+
+fn csv_path_from_args() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let pos = args.iter().position(|a| a == "--csv")?;
+    args.get(pos + 1).cloned()
+}
+
+// pattern given on the command line - `--pattern glider.rle3` - stamped into the middle of the world
+#[derive(Resource, Clone, Debug)]
+struct LoadedPattern(Option<Pattern>);
+
+fn pattern_from_args() -> Option<Pattern> {
+    let args: Vec<String> = std::env::args().collect();
+    let pos = args.iter().position(|a| a == "--pattern")?;
+    match Pattern::load(args.get(pos + 1)?) {
+        Ok(pattern) => Some(pattern),
+        Err(e) => {
+            eprintln!("ignoring --pattern: {}", e);
+            None
+        }
+    }
+}
+
+// best rules of a report from --explore - `--rules rules_42.csv` - show up as #1, #2, ... next to the presets
+#[derive(Resource, Clone, Debug)]
+struct ExploredRules(Vec<Rule>);
+
+fn explored_from_args() -> Vec<Rule> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.iter().position(|a| a == "--rules").and_then(|pos| args.get(pos + 1)) else {
+        return Vec::new();
+    };
+    match explore::load_report(path, explore::TOP) {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("ignoring --rules: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// session given on the command line - `--load saves/slot_1.gol3d` - skips the menu and carries on from there
+#[derive(Resource, Clone, Debug)]
+struct LoadedSession(Option<Session>);
+
+fn session_from_args() -> Option<Session> {
+    let args: Vec<String> = std::env::args().collect();
+    let pos = args.iter().position(|a| a == "--load")?;
+    match Session::load(args.get(pos + 1)?) {
+        Ok(session) => Some(session),
+        Err(e) => {
+            eprintln!("ignoring --load: {}", e);
+            None
+        }
+    }
+}
+
+// Lenia instead of a rule - `--lenia "radius=4,mu=0.15"` tunes it and selects it up front
 #[derive(Resource, Clone, Copy, Debug)]
-struct SelectedRule(pub RuleSet);
+struct LeniaMode {
+    params: LeniaParams,
+    selected: bool,
+}
+
+fn lenia_from_args() -> LeniaMode {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(value) = args.iter().position(|a| a == "--lenia").and_then(|pos| args.get(pos + 1)) else {
+        return LeniaMode { params: LeniaParams::default(), selected: false };
+    };
+    match value.parse() {
+        Ok(params) => LeniaMode { params, selected: true },
+        Err(e) => {
+            eprintln!("ignoring --lenia: {}", e);
+            LeniaMode { params: LeniaParams::default(), selected: false }
+        }
+    }
+}
+
+#[derive(Component)]
+struct LeniaButton;
+
+// buttons of one kind the cursor just entered, left or pressed
+type ButtonChanges<'w, 's, T> = Query<'w, 's, (&'static Interaction, &'static mut BackgroundColor), (Changed<Interaction>, With<T>)>;
+
+fn lenia_button_interactions(
+    mut interactions: ButtonChanges<LeniaButton>,
+    mut lenia: ResMut<LeniaMode>,
+    mut next_state: ResMut<NextState<AppState>>
+) {
+    for (interaction, mut bg) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => {
+                lenia.selected = true;
+                *bg = BackgroundColor(PURPLE_300.into());
+                next_state.set(AppState::InGame);
+            }
+            Interaction::Hovered => *bg = BackgroundColor(PURPLE_500.into()),
+            Interaction::None => *bg = BackgroundColor(PURPLE_600.into()),
+        }
+    }
+}
 
 #[derive(Component)]
 struct RuleButton {
-    rule: RuleSet,
+    rule: Rule,
 }
 
 fn rule_buttons_interactions(
@@ -667,6 +1573,7 @@ fn rule_buttons_interactions(
         (Changed<Interaction>, With<Button>)
     >,
     mut selected: ResMut<SelectedRule>,
+    mut lenia: ResMut<LeniaMode>,
     mut next_state: ResMut<NextState<AppState>>
 ) {
 
@@ -678,7 +1585,8 @@ fn rule_buttons_interactions(
         match *interaction {
             Interaction::Pressed => {
                 // update selected rule enum
-                selected.0 = rule_btn.rule;
+                selected.0 = rule_btn.rule.clone();
+                lenia.selected = false;
                 *bg = sel_col.clone();
                 next_state.set(AppState::InGame);
             }
@@ -692,6 +1600,126 @@ fn rule_buttons_interactions(
     }
 }
 
+// A menu row that only picks something for later - the resource holds what's picked until the game starts
+trait Choice: Resource + Copy + PartialEq {
+    // whether button shows up as the picked one
+    fn shows(&self, button: &Self) -> bool {
+        self == button
+    }
+}
+
+// one button of a Choice row - holds what pressing it picks
+#[derive(Component)]
+struct ChoiceButton<C: Choice>(C);
+
+type ChoicePresses<'w, 's, C> = Query<'w, 's, (&'static Interaction, &'static ChoiceButton<C>), (Changed<Interaction>, With<Button>)>;
+
+fn choice_buttons_interactions<C: Choice>(
+    interactions: ChoicePresses<C>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor, &ChoiceButton<C>)>,
+    mut selected: ResMut<C>,
+) {
+    if interactions.is_empty() {
+        return;
+    }
+
+    for (interaction, button) in &interactions {
+        if *interaction == Interaction::Pressed && !selected.shows(&button.0) {
+            *selected = button.0;
+        }
+    }
+
+    // recolor all of them so the previously selected button loses its highlight
+    for (interaction, mut bg, button) in &mut buttons {
+        *bg = if selected.shows(&button.0) {
+            BackgroundColor(PURPLE_300.into())
+        } else if *interaction == Interaction::Hovered {
+            BackgroundColor(PURPLE_500.into())
+        } else {
+            BackgroundColor(PURPLE_600.into())
+        };
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+struct SelectedBoundary(pub Boundary);
+
+impl Choice for SelectedBoundary {}
+
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+struct SelectedSeeding(pub Seeding);
+
+impl Choice for SelectedSeeding {
+    // a radius from --seeding stays if the same kind of soup is picked again
+    fn shows(&self, button: &Self) -> bool {
+        self.0.name() == button.0.name()
+    }
+}
+
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct SeedButton;
+
+fn seed_button_interactions(
+    mut interactions: ButtonChanges<SeedButton>,
+    mut seed: ResMut<SelectedSeed>,
+) {
+    for (interaction, mut bg) in &mut interactions {
+        match *interaction {
+            Interaction::Pressed => {
+                seed.0 = Game::random_seed();
+                *bg = BackgroundColor(PURPLE_300.into());
+            }
+            Interaction::Hovered => {
+                *bg = BackgroundColor(PURPLE_500.into());
+            }
+            Interaction::None => {
+                *bg = BackgroundColor(PURPLE_600.into());
+            }
+        }
+    }
+}
+
+// number keys append a digit to the seed, backspace drops the last one and delete clears it.
+// A seed that came from elsewhere (random, --seed, the last game) isn't the one typed last - the first digit
+// starts a new number then instead of overflowing the 20 random ones
+fn seed_typing(keys: Res<ButtonInput<KeyCode>>, mut seed: ResMut<SelectedSeed>, mut typed: Local<Option<u64>>) {
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+        KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+
+    for (digit, key) in DIGITS.iter().enumerate() {
+        if keys.just_pressed(*key) {
+            let current = if *typed == Some(seed.0) { seed.0 } else { 0 };
+            // stays as is once another digit would overflow
+            if let Some(next) = current.checked_mul(10).and_then(|s| s.checked_add(digit as u64)) {
+                seed.0 = next;
+                *typed = Some(next);
+            }
+        }
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        seed.0 /= 10;
+        *typed = Some(seed.0);
+    }
+    if keys.just_pressed(KeyCode::Delete) {
+        seed.0 = 0;
+        *typed = Some(0);
+    }
+}
+
+fn update_seed_text(seed: Res<SelectedSeed>, mut texts: Query<&mut Text, With<SeedText>>) {
+    if !seed.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        *text = Text::new(format!("Seed: {}", seed.0));
+    }
+}
+
 fn despawn_menu(mut commands: Commands, cameras: Query<Entity, With<MenuCamera>>, uis: Query<Entity, With<MenuUI>>) {
     for cam in cameras.iter() {
         commands.entity(cam).despawn();
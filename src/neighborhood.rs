@@ -0,0 +1,186 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::lattice::Lattice;
+use crate::rule::{RuleError, MAX_COUNT};
+
+// past this radius not even a von Neumann neighborhood fits into MAX_COUNT
+const MAX_RADIUS: u8 = 6;
+
+// Which cells around (0,0,0) count as neighbors
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Neighborhood {
+    // full cube of side 2r+1 - radius 1 is the classic 26 cells
+    Moore(u8),
+    // cells within manhattan distance r - radius 1 are the 6 face neighbors
+    VonNeumann(u8),
+    // moore radius 1 with a weight per kind of neighbor - [face, edge, corner], e.g. [2, 1, 0]
+    Weighted([u8; 3]),
+    // user supplied offsets relative to the cell with their weight
+    Custom(Vec<([i32; 3], u8)>),
+    // the 12 face neighbors on the face centered cubic lattice (see lattice.rs)
+    Fcc,
+    // the 14 face neighbors on the body centered cubic lattice
+    Bcc,
+}
+
+impl Default for Neighborhood {
+    fn default() -> Neighborhood {
+        Neighborhood::Moore(1)
+    }
+}
+
+impl Neighborhood {
+    // relative offsets of all neighbors with what an alive one adds to the count - the cell itself
+    // is never included and neither are offsets weighted 0
+    pub fn offsets(&self) -> Vec<([isize; 3], usize)> {
+        match self {
+            Neighborhood::Moore(r) => cube_offsets(*r, |_| 1),
+            Neighborhood::VonNeumann(r) => {
+                let max = *r as isize;
+                cube_offsets(*r, |[dx, dy, dz]| (dx.abs() + dy.abs() + dz.abs() <= max) as usize)
+            }
+            // how many axes an offset moves along tells face (1), edge (2) and corner (3) apart
+            Neighborhood::Weighted(weights) => {
+                cube_offsets(1, |d| weights[d.iter().filter(|&&v| v != 0).count() - 1] as usize)
+            }
+            Neighborhood::Custom(offsets) => offsets
+                .iter()
+                .map(|&([dx, dy, dz], w)| ([dx as isize, dy as isize, dz as isize], w as usize))
+                .filter(|&(_, w)| w > 0)
+                .collect(),
+            Neighborhood::Fcc | Neighborhood::Bcc => self.lattice().offsets().into_iter().map(|d| (d, 1)).collect(),
+        }
+    }
+
+    // the lattice the cells sit on - cubic for everything but FCC and BCC
+    pub fn lattice(&self) -> Lattice {
+        match self {
+            Neighborhood::Fcc => Lattice::Fcc,
+            Neighborhood::Bcc => Lattice::Bcc,
+            _ => Lattice::Cubic,
+        }
+    }
+
+    // "FCC" / "BCC" - they start like other parts of a rule so the rule parser checks for them first
+    pub fn is_lattice(s: &str) -> bool {
+        ["FCC", "BCC"].iter().any(|name| name.eq_ignore_ascii_case(s.trim()))
+    }
+
+    // highest count the neighborhood can reach - all neighbors alive
+    pub fn max_count(&self) -> usize {
+        self.offsets().iter().map(|(_, w)| w).sum()
+    }
+
+    fn check(self, src: &str) -> Result<Neighborhood, RuleError> {
+        let max = self.max_count();
+        if max == 0 || max > MAX_COUNT {
+            return Err(RuleError::Unsupported(format!("neighborhood {} counting up to {}", src, max)));
+        }
+        Ok(self)
+    }
+}
+
+// every offset in the cube of radius r with the weight it gets, skipping the center and weights of 0
+fn cube_offsets(r: u8, weight: impl Fn([isize; 3]) -> usize) -> Vec<([isize; 3], usize)> {
+    let r = r as isize;
+    let mut offsets = Vec::new();
+    for dz in -r..=r {
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if (dx, dy, dz) == (0, 0, 0) {
+                    continue;
+                }
+                let w = weight([dx, dy, dz]);
+                if w > 0 {
+                    offsets.push(([dx, dy, dz], w));
+                }
+            }
+        }
+    }
+    offsets
+}
+
+// "M" / "N" with an optional radius e.g. "M2", face/edge/corner weights "W2,1,0"
+// or an offset list "{1,0,0;-1,0,0;0,2,0}" where each offset can end in a weight "{1,0,0:2;0,1,1}",
+// "FCC" / "BCC" for the other lattices
+impl FromStr for Neighborhood {
+    type Err = RuleError;
+
+    fn from_str(s: &str) -> Result<Neighborhood, RuleError> {
+        let s = s.trim();
+        let bad = || RuleError::BadNeighborhood(s.to_string());
+
+        if s.eq_ignore_ascii_case("FCC") {
+            return Ok(Neighborhood::Fcc);
+        }
+        if s.eq_ignore_ascii_case("BCC") {
+            return Ok(Neighborhood::Bcc);
+        }
+
+        if let Some(list) = s.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+            let mut offsets = Vec::new();
+            for item in list.split(';').map(str::trim).filter(|i| !i.is_empty()) {
+                let (item, weight) = match item.split_once(':') {
+                    Some((item, weight)) => (item, weight.trim().parse::<u8>().ok().filter(|&w| w > 0).ok_or_else(bad)?),
+                    None => (item, 1),
+                };
+                let coords: Vec<i32> = item
+                    .split(',')
+                    .map(|c| c.trim().parse().map_err(|_| bad()))
+                    .collect::<Result<_, _>>()?;
+                match coords[..] {
+                    [0, 0, 0] => return Err(bad()),
+                    [dx, dy, dz] if !offsets.iter().any(|(d, _)| *d == [dx, dy, dz]) => offsets.push(([dx, dy, dz], weight)),
+                    _ => return Err(bad()),
+                }
+            }
+            return Neighborhood::Custom(offsets).check(s);
+        }
+
+        if let Some(list) = s.strip_prefix(['W', 'w']) {
+            let weights: Vec<u8> = list.split(',').map(|w| w.trim().parse().map_err(|_| bad())).collect::<Result<_, _>>()?;
+            let weights = weights.try_into().map_err(|_| bad())?;
+            return Neighborhood::Weighted(weights).check(s);
+        }
+
+        let mut chars = s.chars();
+        let kind = chars.next().ok_or_else(bad)?;
+        let radius = match chars.as_str() {
+            "" => 1,
+            r => r.parse::<u8>().ok().filter(|&r| r > 0 && r <= MAX_RADIUS).ok_or_else(bad)?,
+        };
+        match kind {
+            'M' | 'm' => Neighborhood::Moore(radius).check(s),
+            'N' | 'n' | 'V' | 'v' => Neighborhood::VonNeumann(radius).check(s),
+            _ => Err(bad()),
+        }
+    }
+}
+
+impl fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Neighborhood::Moore(1) => write!(f, "M"),
+            Neighborhood::Moore(r) => write!(f, "M{}", r),
+            Neighborhood::VonNeumann(1) => write!(f, "N"),
+            Neighborhood::VonNeumann(r) => write!(f, "N{}", r),
+            Neighborhood::Weighted([face, edge, corner]) => write!(f, "W{},{},{}", face, edge, corner),
+            Neighborhood::Fcc => write!(f, "FCC"),
+            Neighborhood::Bcc => write!(f, "BCC"),
+            Neighborhood::Custom(offsets) => {
+                write!(f, "{{")?;
+                for (i, ([dx, dy, dz], w)) in offsets.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, "{},{},{}", dx, dy, dz)?;
+                    if *w != 1 {
+                        write!(f, ":{}", w)?;
+                    }
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::UVec3;
+
+use crate::grid::{linear_index, volume, Grid};
+use crate::rule::{Rule, RuleError, ALIVE, DEAD};
+use crate::sparse::SparseWorld;
+
+// longest line the saver writes
+const LINE: usize = 70;
+// largest block a header may ask for - the cells get allocated up front
+const MAX_VOLUME: usize = 1 << 28;
+
+// RLE extended to 3D (.rle3) - e.g. Bays' glider:
+//
+//   #C comment lines start with #
+//   x = 4, y = 3, z = 2, rule = B5/S4-5
+//   b2o$o2bo/b2o$o2bo$b2o!
+//
+// Header is the size of the block and optionally the rule (rest of the line - rules have commas).
+// Body runs along x, '$' ends a row (next y), '/' ends a layer (next z), '!' ends the pattern -
+// all of them can be prefixed with a run count. 'b' / '.' is dead, 'o' alive and dying states of
+// Generations rules are written like Golly does: 'A' = 1 ... 'X' = 24, 'pA' = 25 ... 'yO' = 255.
+// Cells left out at the end of a row / layer are dead
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pattern {
+    pub size: UVec3,
+    pub cells: Vec<u8>, // states in linear_index order
+    pub rule: Option<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    Io(String),
+    MissingHeader,
+    BadHeader(String),
+    BadRule(RuleError),
+    TooLarge(UVec3),
+    BadTag(char, usize), // unknown character and the line it's on
+    OutOfBounds(usize),  // line where the body runs past the size from the header
+    BadState(u8),        // state the pattern's rule (or the one it's stamped into) doesn't have
+    Unterminated,        // no '!' at the end
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "couldn't access pattern file: {}", e),
+            PatternError::MissingHeader => write!(f, "missing 'x = .., y = .., z = ..' header line"),
+            PatternError::BadHeader(s) => write!(f, "'{}' is not a valid header", s),
+            PatternError::BadRule(e) => write!(f, "bad rule in header: {}", e),
+            PatternError::TooLarge(s) => write!(f, "{}x{}x{} is too large for a pattern", s.x, s.y, s.z),
+            PatternError::BadTag(c, line) => write!(f, "unexpected '{}' on line {}", c, line),
+            PatternError::OutOfBounds(line) => write!(f, "cells on line {} lie outside the size from the header", line),
+            PatternError::BadState(s) => write!(f, "state {} is not part of the rule", s),
+            PatternError::Unterminated => write!(f, "pattern doesn't end with '!'"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<RuleError> for PatternError {
+    fn from(e: RuleError) -> PatternError {
+        PatternError::BadRule(e)
+    }
+}
+
+impl Pattern {
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u8 {
+        self.cells[linear_index(x, y, z, self.size)]
+    }
+
+    // everything that isn't dead, cut down to its bounding box - and where that box's corner was
+    pub fn from_cells(cells: impl Iterator<Item = ([i64; 3], u8)>, rule: Option<Rule>) -> (Pattern, [i64; 3]) {
+        let cells: Vec<([i64; 3], u8)> = cells.filter(|&(_, s)| s != DEAD).collect();
+        let Some(&(first, _)) = cells.first() else {
+            return (Pattern { size: UVec3::ZERO, cells: Vec::new(), rule }, [0; 3]);
+        };
+
+        let (mut lo, mut hi) = (first, first);
+        for (p, _) in &cells {
+            lo = std::array::from_fn(|a| lo[a].min(p[a]));
+            hi = std::array::from_fn(|a| hi[a].max(p[a]));
+        }
+        let size = UVec3::new((hi[0] - lo[0] + 1) as u32, (hi[1] - lo[1] + 1) as u32, (hi[2] - lo[2] + 1) as u32);
+        let mut pattern = Pattern { size, cells: vec![DEAD; volume(size)], rule };
+        for (p, state) in cells {
+            let i = linear_index((p[0] - lo[0]) as usize, (p[1] - lo[1]) as usize, (p[2] - lo[2]) as usize, size);
+            pattern.cells[i] = state;
+        }
+        (pattern, lo)
+    }
+
+    // BadState for the first cell a rule with that many states doesn't have
+    pub fn check_states(&self, states: u8) -> Result<(), PatternError> {
+        match self.cells.iter().find(|&&s| s >= states) {
+            Some(&state) => Err(PatternError::BadState(state)),
+            None => Ok(()),
+        }
+    }
+
+    // sets the pattern's cells into the grid with its corner at offset - cells outside the grid are left out
+    pub fn stamp(&self, grid: &mut Grid, offset: [i64; 3]) {
+        let size = grid.size().to_array().map(|v| v as i64);
+        self.for_each_cell(offset, |p, state| {
+            if (0..3).all(|a| p[a] >= 0 && p[a] < size[a]) {
+                grid.set(p[0] as usize, p[1] as usize, p[2] as usize, state);
+            }
+        });
+    }
+
+    // same for an unbounded world - nothing gets cut off there
+    pub fn stamp_sparse(&self, world: &mut SparseWorld, offset: [i64; 3]) {
+        self.for_each_cell(offset, |p, state| world.set(p, state));
+    }
+
+    fn for_each_cell(&self, offset: [i64; 3], mut f: impl FnMut([i64; 3], u8)) {
+        for x in 0..self.size.x as usize {
+            for y in 0..self.size.y as usize {
+                for z in 0..self.size.z as usize {
+                    let p = [offset[0] + x as i64, offset[1] + y as i64, offset[2] + z as i64];
+                    f(p, self.get(x, y, z));
+                }
+            }
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Pattern, PatternError> {
+        std::fs::read_to_string(path).map_err(|e| PatternError::Io(e.to_string()))?.parse()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), PatternError> {
+        std::fs::write(path, self.to_string()).map_err(|e| PatternError::Io(e.to_string()))
+    }
+}
+
+// "x = 4, y = 3, z = 2, rule = B5/S4-5"
+fn parse_header(line: &str) -> Result<(UVec3, Option<Rule>), PatternError> {
+    let bad = || PatternError::BadHeader(line.to_string());
+    // the rule can contain commas itself - it's always the rest of the line
+    let (dims, rule) = match line.find("rule") {
+        Some(i) => {
+            let (_, value) = line[i..].split_once('=').ok_or_else(bad)?;
+            (&line[..i], Some(value.trim().parse::<Rule>()?))
+        }
+        None => (line, None),
+    };
+
+    let mut size = [None; 3];
+    for item in dims.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        let (key, value) = item.split_once('=').ok_or_else(bad)?;
+        let axis = match key.trim() {
+            "x" => 0,
+            "y" => 1,
+            "z" => 2,
+            _ => return Err(bad()),
+        };
+        size[axis] = Some(value.trim().parse::<u32>().map_err(|_| bad())?);
+    }
+    match size {
+        [Some(x), Some(y), Some(z)] => Ok((UVec3::new(x, y, z), rule)),
+        _ => Err(bad()),
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Pattern, PatternError> {
+        let mut lines = s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+        let (_, header) = lines.next().ok_or(PatternError::MissingHeader)?;
+        let (size, rule) = parse_header(header)?;
+        if size.to_array().iter().try_fold(1usize, |v, &d| v.checked_mul(d as usize)).is_none_or(|v| v > MAX_VOLUME) {
+            return Err(PatternError::TooLarge(size));
+        }
+
+        let mut pattern = Pattern { size, cells: vec![DEAD; volume(size)], rule };
+        let (mut x, mut y, mut z) = (0usize, 0usize, 0usize);
+        let mut run: Option<usize> = None;
+        let mut prefix: Option<char> = None; // 'p'..'y' of a two letter state
+        for (n, line) in lines {
+            let line_no = n + 1;
+            for c in line.chars().filter(|c| !c.is_whitespace()) {
+                if let Some(d) = c.to_digit(10) {
+                    run = Some(run.unwrap_or(0).saturating_mul(10).saturating_add(d as usize));
+                    continue;
+                }
+                let count = run.take().unwrap_or(1);
+
+                let state = match (prefix.take(), c) {
+                    (None, 'b' | '.') => DEAD,
+                    (None, 'o') => ALIVE,
+                    (None, 'A'..='X') => c as u8 - b'A' + 1,
+                    (Some(p), 'A'..='X') => {
+                        let state = 25 + (p as u32 - 'p' as u32) * 24 + (c as u32 - 'A' as u32);
+                        u8::try_from(state).map_err(|_| PatternError::BadTag(c, line_no))?
+                    }
+                    (None, 'p'..='y') => {
+                        prefix = Some(c);
+                        // the count belongs to the whole two letter state
+                        run = Some(count);
+                        continue;
+                    }
+                    (None, '$') => {
+                        (x, y) = (0, y.saturating_add(count));
+                        continue;
+                    }
+                    (None, '/') => {
+                        (x, y, z) = (0, 0, z.saturating_add(count));
+                        continue;
+                    }
+                    (None, '!') => return Ok(pattern),
+                    _ => return Err(PatternError::BadTag(c, line_no)),
+                };
+
+                if pattern.rule.as_ref().is_some_and(|r| state >= r.states) {
+                    return Err(PatternError::BadState(state));
+                }
+                if state != DEAD {
+                    if x.saturating_add(count) > size.x as usize || y >= size.y as usize || z >= size.z as usize {
+                        return Err(PatternError::OutOfBounds(line_no));
+                    }
+                    for i in x..x + count {
+                        pattern.cells[linear_index(i, y, z, size)] = state;
+                    }
+                }
+                x = x.saturating_add(count);
+            }
+        }
+        Err(PatternError::Unterminated)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "x = {}, y = {}, z = {}", self.size.x, self.size.y, self.size.z)?;
+        if let Some(rule) = &self.rule {
+            write!(f, ", rule = {}", rule)?;
+        }
+        writeln!(f)?;
+
+        // 2 state patterns use b/o like plain RLE
+        let two_states = self.rule.as_ref().is_none_or(|r| r.states <= 2) && self.cells.iter().all(|&s| s <= ALIVE);
+        let tag = |state: u8| -> String {
+            match state {
+                DEAD if two_states => "b".to_string(),
+                DEAD => ".".to_string(),
+                ALIVE if two_states => "o".to_string(),
+                1..=24 => ((b'A' + state - 1) as char).to_string(),
+                _ => format!("{}{}", (b'p' + (state - 25) / 24) as char, (b'A' + (state - 25) % 24) as char),
+            }
+        };
+        let run = |count: usize, tag: &str| if count == 1 { tag.to_string() } else { format!("{}{}", count, tag) };
+
+        // runs of the non empty rows, moving the "cursor" with $ and / in between
+        let mut items = Vec::new();
+        let (mut cy, mut cz) = (0, 0);
+        for z in 0..self.size.z as usize {
+            for y in 0..self.size.y as usize {
+                let row: Vec<u8> = (0..self.size.x as usize).map(|x| self.get(x, y, z)).collect();
+                let Some(end) = row.iter().rposition(|&s| s != DEAD) else {
+                    continue;
+                };
+                if z > cz {
+                    items.push(run(z - cz, "/"));
+                    (cy, cz) = (0, z);
+                }
+                if y > cy {
+                    items.push(run(y - cy, "$"));
+                    cy = y;
+                }
+                let mut x = 0;
+                while x <= end {
+                    let len = row[x..=end].iter().take_while(|&&s| s == row[x]).count();
+                    items.push(run(len, &tag(row[x])));
+                    x += len;
+                }
+            }
+        }
+        items.push("!".to_string());
+
+        let mut line = 0;
+        for item in items {
+            if line + item.len() > LINE {
+                writeln!(f)?;
+                line = 0;
+            }
+            write!(f, "{}", item)?;
+            line += item.len();
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::CountSet;
+    use crate::seeding::GLIDER;
+
+    fn round_trip(pattern: &Pattern) {
+        let text = pattern.to_string();
+        assert!(text.lines().all(|l| l.len() <= LINE), "{}", text);
+        assert_eq!(text.parse::<Pattern>().as_ref(), Ok(pattern), "{}", text);
+    }
+
+    #[test]
+    fn parses_the_glider_from_the_docs() {
+        let pattern: Pattern = "#C Bays' glider\nx = 4, y = 3, z = 2, rule = B5/S4-5\nb2o$o2bo/b2o$o2bo$b2o!\n".parse().unwrap();
+        assert_eq!(pattern.size, UVec3::new(4, 3, 2));
+        assert_eq!(pattern.rule, Some(Rule::BAYS));
+        assert_eq!(pattern.cells.iter().filter(|&&s| s == ALIVE).count(), 10);
+        assert_eq!(pattern.get(1, 0, 0), ALIVE);
+        assert_eq!(pattern.get(0, 0, 0), DEAD);
+        assert_eq!(pattern.get(3, 1, 1), ALIVE);
+    }
+
+    #[test]
+    fn round_trips_two_states() {
+        let cells = GLIDER.iter().map(|c| (c.map(|v| v as i64), ALIVE));
+        let (glider, _) = Pattern::from_cells(cells, Some(Rule::BAYS));
+        round_trip(&glider);
+        // without a rule header
+        round_trip(&Pattern { rule: None, ..glider });
+    }
+
+    #[test]
+    fn round_trips_multistate_runs_and_separators() {
+        // runs of one state, single and two letter states, empty rows and layers in between
+        let mut cells = Vec::new();
+        for x in 0..5 {
+            cells.push(([x, 0, 0], 3));
+        }
+        cells.push(([6, 0, 0], 1));
+        cells.push(([7, 0, 0], 24));
+        cells.push(([0, 4, 0], 25));
+        cells.push(([1, 4, 0], 200));
+        cells.push(([2, 2, 3], 254));
+        cells.push(([9, 5, 6], 2));
+        let rule = Rule::generations(CountSet::range(4, 4), CountSet::range(4, 4), 255);
+        let (pattern, lo) = Pattern::from_cells(cells.clone().into_iter(), Some(rule));
+        assert_eq!(lo, [0; 3]);
+        assert_eq!(pattern.size, UVec3::new(10, 6, 7));
+        let text = pattern.to_string();
+        assert!(text.contains("5C") && text.contains('$') && text.contains("3/"), "{}", text);
+        round_trip(&pattern);
+        for (p, state) in cells {
+            assert_eq!(pattern.get(p[0] as usize, p[1] as usize, p[2] as usize), state);
+        }
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        // every other cell alive - nothing but single runs
+        let cells = (0..400).filter(|x| x % 2 == 0).map(|x| ([x, 0, 0], ALIVE));
+        let (pattern, _) = Pattern::from_cells(cells, None);
+        assert!(pattern.to_string().lines().count() > 3);
+        round_trip(&pattern);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let err = |s: &str| s.parse::<Pattern>().unwrap_err();
+        assert_eq!(err(""), PatternError::MissingHeader);
+        assert_eq!(err("#C nothing but comments\n"), PatternError::MissingHeader);
+        assert_eq!(err("x = 4, y = 3\no!"), PatternError::BadHeader("x = 4, y = 3".to_string()));
+        assert_eq!(err("x = 4, y = 3, w = 2\no!"), PatternError::BadHeader("x = 4, y = 3, w = 2".to_string()));
+        assert!(matches!(err("x = 1, y = 1, z = 1, rule = Q\no!"), PatternError::BadRule(_)));
+        assert_eq!(err("x = 100000, y = 100000, z = 100000\n!"), PatternError::TooLarge(UVec3::splat(100000)));
+        assert_eq!(err("x = 1, y = 1, z = 1\n\nk!"), PatternError::BadTag('k', 3));
+        assert_eq!(err("x = 1, y = 1, z = 1\nq!"), PatternError::BadTag('!', 2));
+        assert_eq!(err("x = 1, y = 1, z = 1\n2o!"), PatternError::OutOfBounds(2));
+        assert_eq!(err("x = 1, y = 1, z = 1\n$o!"), PatternError::OutOfBounds(2));
+        assert_eq!(err("x = 1, y = 1, z = 1, rule = B5/S4-5\nB!"), PatternError::BadState(2));
+        assert_eq!(err("x = 1, y = 1, z = 1\no"), PatternError::Unterminated);
+        assert!(matches!(Pattern::load("/nonexistent/glider.rle3"), Err(PatternError::Io(_))));
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let cells = GLIDER.iter().map(|c| (c.map(|v| v as i64), ALIVE));
+        let (glider, _) = Pattern::from_cells(cells, Some(Rule::BAYS));
+        let path = std::env::temp_dir().join(format!("gol3d_pattern_{}.rle3", std::process::id()));
+        let path = path.to_str().unwrap();
+        glider.save(path).unwrap();
+        let loaded = Pattern::load(path);
+        let _ = std::fs::remove_file(path);
+        assert_eq!(loaded, Ok(glider));
+    }
+
+    #[test]
+    fn stamps_cut_at_the_grid_but_not_unbounded() {
+        let (pattern, _) = Pattern::from_cells([([0, 0, 0], ALIVE), ([2, 1, 1], ALIVE)].into_iter(), None);
+        let mut grid = Grid::new(UVec3::splat(3), 2);
+        pattern.stamp(&mut grid, [-1, 0, 0]);
+        assert_eq!(grid.iter_alive().collect::<Vec<_>>(), vec![(1, 1, 1)]);
+
+        let mut world = SparseWorld::default();
+        pattern.stamp_sparse(&mut world, [-1, 0, 0]);
+        assert_eq!(world.get([-1, 0, 0]), ALIVE);
+        assert_eq!(world.get([1, 1, 1]), ALIVE);
+
+        assert_eq!(Pattern { cells: vec![3], size: UVec3::ONE, rule: None }.check_states(3), Err(PatternError::BadState(3)));
+        assert_eq!(pattern.check_states(2), Ok(()));
+    }
+}
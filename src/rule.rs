@@ -0,0 +1,580 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::neighborhood::Neighborhood;
+
+// highest neighbor count a rule can refer to (342 = radius 3 moore neighborhood fits)
+pub const MAX_COUNT: usize = 511;
+const WORDS: usize = (MAX_COUNT + 1) / 64;
+
+// cell states - anything above ALIVE is a dying (refractory) cell in a Generations rule
+pub const DEAD: u8 = 0;
+pub const ALIVE: u8 = 1;
+
+// chances are in millionths - this one always happens
+pub const CERTAIN: u32 = 1_000_000;
+
+// set of neighbor counts stored as a bitmask - bit n set => count n is in the set
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct CountSet([u64; WORDS]);
+
+impl CountSet {
+    pub const EMPTY: CountSet = CountSet([0; WORDS]);
+
+    // inclusive range of counts, usable in consts
+    pub const fn range(lo: usize, hi: usize) -> CountSet {
+        let mut bits = [0; WORDS];
+        let mut n = lo;
+        while n <= hi {
+            bits[n / 64] |= 1 << (n % 64);
+            n += 1;
+        }
+        CountSet(bits)
+    }
+
+    pub const fn union(self, other: CountSet) -> CountSet {
+        let mut bits = self.0;
+        let mut i = 0;
+        while i < WORDS {
+            bits[i] |= other.0[i];
+            i += 1;
+        }
+        CountSet(bits)
+    }
+
+    #[inline]
+    pub fn contains(&self, count: usize) -> bool {
+        count <= MAX_COUNT && self.0[count / 64] & (1 << (count % 64)) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..=MAX_COUNT).filter(|&n| self.contains(n))
+    }
+
+    // parses lists like "5,6", "5-7" or "2,4-6" - empty string => empty set
+    fn parse(s: &str) -> Result<CountSet, RuleError> {
+        let mut set = CountSet::EMPTY;
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (lo, hi) = match item.split_once('-') {
+                Some((lo, hi)) => (parse_count(lo)?, parse_count(hi)?),
+                None => {
+                    let n = parse_count(item)?;
+                    (n, n)
+                }
+            };
+            if lo > hi {
+                return Err(RuleError::BadRange(item.to_string()));
+            }
+            set = set.union(CountSet::range(lo, hi));
+        }
+        Ok(set)
+    }
+}
+
+// like CountSet::parse but every item can end in a chance - "4,5-6:0.5" => {4, 5, 6} with 5 and 6 at 0.5
+fn parse_odds(s: &str) -> Result<(CountSet, Vec<(usize, u32)>), RuleError> {
+    let mut set = CountSet::EMPTY;
+    let mut odds: Vec<(usize, u32)> = Vec::new();
+    for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        let (counts, chance) = match item.split_once(':') {
+            Some((counts, chance)) => (CountSet::parse(counts)?, parse_chance(chance)?),
+            None => (CountSet::parse(item)?, CERTAIN),
+        };
+        set = set.union(counts);
+        // a count given twice takes the chance it was given last
+        odds.retain(|&(n, _)| !counts.contains(n));
+        odds.extend(counts.iter().filter(|_| chance != CERTAIN).map(|n| (n, chance)));
+    }
+    odds.sort_unstable();
+    Ok((set, odds))
+}
+
+// "0.25" => 250000 millionths
+fn parse_chance(s: &str) -> Result<u32, RuleError> {
+    match s.trim().parse::<f64>() {
+        Ok(p) if (0. ..=1.).contains(&p) => Ok((p * CERTAIN as f64).round() as u32),
+        _ => Err(RuleError::BadChance(s.to_string())),
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, RuleError> {
+    let n: usize = s.trim().parse().map_err(|_| RuleError::BadCount(s.to_string()))?;
+    if n > MAX_COUNT {
+        return Err(RuleError::CountTooLarge(n));
+    }
+    Ok(n)
+}
+
+// writes the set back in its shortest form, e.g. {2,4,5,6} => "2,4-6"
+impl fmt::Display for CountSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        let mut n = 0;
+        while n <= MAX_COUNT {
+            if !self.contains(n) {
+                n += 1;
+                continue;
+            }
+            let start = n;
+            while n < MAX_COUNT && self.contains(n + 1) {
+                n += 1;
+            }
+            if !first {
+                write!(f, ",")?;
+            }
+            first = false;
+            if start == n {
+                write!(f, "{}", n)?;
+            } else {
+                write!(f, "{}-{}", start, n)?;
+            }
+            n += 1;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    BadCount(String),
+    BadRange(String),
+    CountTooLarge(usize),
+    BadFormat(String),
+    BadStates(String),
+    BadNeighborhood(String),
+    BadChance(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::BadCount(s) => write!(f, "'{}' is not a neighbor count", s),
+            RuleError::BadRange(s) => write!(f, "range '{}' is reversed", s),
+            RuleError::CountTooLarge(n) => write!(f, "neighbor count {} is above the maximum of {}", n, MAX_COUNT),
+            RuleError::BadFormat(s) => write!(f, "'{}' is neither B/S nor S/B/states/neighborhood notation", s),
+            RuleError::BadStates(s) => write!(f, "'{}' is not a state count between 2 and 255", s),
+            RuleError::BadNeighborhood(s) => write!(f, "'{}' is not a neighborhood (M, N, M2, N3, W2,1,0, FCC, BCC or {{x,y,z;x,y,z:weight;...}})", s),
+            RuleError::BadChance(s) => write!(f, "'{}' is not a chance between 0 and 1", s),
+            RuleError::Unsupported(s) => write!(f, "'{}' is not supported", s),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+// What makes a rule stochastic - birth / survival counts that only apply with a chance and
+// the temperature, the chance of every cell flipping (dead => alive, anything else => dead) each generation.
+// Counts without a chance always apply like in a deterministic rule. Chances are in millionths
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Odds {
+    pub birth: Vec<(usize, u32)>, // sorted by count
+    pub survival: Vec<(usize, u32)>,
+    pub temperature: u32,
+}
+
+impl Odds {
+    pub const NONE: Odds = Odds { birth: Vec::new(), survival: Vec::new(), temperature: 0 };
+
+    pub fn is_none(&self) -> bool {
+        self.birth.is_empty() && self.survival.is_empty() && self.temperature == 0
+    }
+}
+
+// Side conditions of a directional rule - on top of the total count being in the birth / survival set the
+// alive neighbors below (lower y), beside (same y) and above (higher y) each have to be in their own set,
+// a side without a set can be anything. Written as D (down), L (level) and U (up) groups after the counts:
+// "B5-6D2-9L1-8/S4-5" => born with 5-6 alive neighbors of which at least 2 below and 1 beside.
+// Weighting the axes differently works with a weighted neighborhood instead - "{0,-1,0:2;...}"
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Sides {
+    pub birth: [Option<CountSet>; 3], // below, beside, above
+    pub survival: [Option<CountSet>; 3],
+}
+
+impl Sides {
+    pub const NONE: Sides = Sides { birth: [None; 3], survival: [None; 3] };
+
+    pub fn is_none(&self) -> bool {
+        *self == Sides::NONE
+    }
+}
+
+// letters of below, beside and above
+const SIDE_NAMES: [char; 3] = ['D', 'L', 'U'];
+
+fn side_of(c: char) -> Option<usize> {
+    SIDE_NAMES.iter().position(|&s| s == c.to_ascii_uppercase())
+}
+
+fn sides_allow(conditions: &[Option<CountSet>; 3], sides: [usize; 3]) -> bool {
+    conditions.iter().zip(sides).all(|(set, n)| set.is_none_or(|set| set.contains(n)))
+}
+
+// counts, their chances and the side groups of a birth / survival part
+type Condition = (CountSet, Vec<(usize, u32)>, [Option<CountSet>; 3]);
+
+// a birth / survival part - counts with optional chances followed by optional side groups, "5-6:0.5D2-9L1".
+// Only side groups => the total can be anything
+fn parse_condition(s: &str) -> Result<Condition, RuleError> {
+    let mut sides = [None; 3];
+    let Some(first) = s.find(|c| side_of(c).is_some()) else {
+        let (set, odds) = parse_odds(s)?;
+        return Ok((set, odds, sides));
+    };
+
+    let mut rest = &s[first..];
+    while let Some(side) = rest.chars().next().and_then(side_of) {
+        let end = rest[1..].find(|c| side_of(c).is_some()).map_or(rest.len(), |i| i + 1);
+        if sides[side].is_some() {
+            return Err(RuleError::BadFormat(s.to_string()));
+        }
+        sides[side] = Some(CountSet::parse(&rest[1..end])?);
+        rest = &rest[end..];
+    }
+    let (set, odds) = match s[..first].trim() {
+        "" => (CountSet::range(0, MAX_COUNT), Vec::new()),
+        counts => parse_odds(counts)?,
+    };
+    Ok((set, odds, sides))
+}
+
+fn chance_of(odds: &[(usize, u32)], count: usize) -> u32 {
+    odds.iter().find(|&&(n, _)| n == count).map_or(CERTAIN, |&(_, chance)| chance)
+}
+
+// true with the given chance - roll is uniform over all u32
+#[inline]
+fn hits(roll: u32, chance: u32) -> bool {
+    (roll as u64 * CERTAIN as u64) >> 32 < chance as u64
+}
+
+// The random number a stochastic rule uses for a cell in a generation - a hash (splitmix64) of the
+// game's seed, the generation and the cell. Every cell gets the same draw no matter how the world is
+// split up into chunks / threads, and a generation recomputed after stepping back comes out the same
+pub fn noise(seed: u64, generation: usize, cell: [i64; 3]) -> u64 {
+    let mix = |mut z: u64| {
+        z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    [generation as u64, cell[0] as u64, cell[1] as u64, cell[2] as u64].into_iter().fold(mix(seed), |h, v| mix(h ^ v))
+}
+
+// Outer totalistic rule - the next state of a cell only depends on
+// its own state and how many of its neighbors are alive (weighted neighborhoods add up the weights instead).
+// With more than 2 states it's a "Generations" rule: a cell that fails survival
+// goes through states 2..states-1 before it's dead, and only ALIVE cells count as neighbors.
+// With odds it's stochastic (see Odds) - "B5:0.5/S4-5/T0.001",
+// with side conditions directional (see Sides) - "B5-6D2-9/S4-5"
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Rule {
+    pub birth: CountSet,
+    pub survival: CountSet,
+    pub states: u8,
+    pub neighborhood: Neighborhood,
+    pub odds: Odds,
+    pub sides: Sides,
+}
+
+impl Rule {
+    // the old hardcoded rule sets - recommended 96x96x96
+    pub const BALANCED: Rule = Rule::new(CountSet::range(5, 6), CountSet::range(5, 7));
+    pub const DENSE: Rule = Rule::new(CountSet::range(5, 6), CountSet::range(4, 9));
+    pub const SPARSE: Rule = Rule::new(CountSet::range(5, 5), CountSet::range(3, 5));
+    // todo: find better params - `--explore` ranks candidates
+    pub const CHAOTIC: Rule = Rule::new(CountSet::range(5, 6), CountSet::range(5, 8));
+    // a born cell never dies
+    pub const NO_DEATH: Rule = Rule::new(CountSet::range(5, 5), CountSet::range(0, 26));
+    // 3d take on Brian's Brain - "4/4/5/M"
+    pub const BRAIN: Rule = Rule::generations(CountSet::range(4, 4), CountSet::range(4, 4), 5);
+    // Carter Bays' 3D Life 4555 - home of the first 3d glider (see seeding::GLIDER)
+    pub const BAYS: Rule = Rule::new(CountSet::range(5, 5), CountSet::range(4, 5));
+
+    // presets with their display names - used for the menu
+    pub const PRESETS: [(&'static str, Rule); 7] = [
+        ("Balanced", Rule::BALANCED),
+        ("Dense", Rule::DENSE),
+        ("Sparse", Rule::SPARSE),
+        ("Chaotic", Rule::CHAOTIC),
+        ("No Death", Rule::NO_DEATH),
+        ("Brain", Rule::BRAIN),
+        ("Bays", Rule::BAYS),
+    ];
+
+    pub const fn new(birth: CountSet, survival: CountSet) -> Rule {
+        Rule::generations(birth, survival, 2)
+    }
+
+    pub const fn generations(birth: CountSet, survival: CountSet, states: u8) -> Rule {
+        Rule { birth, survival, states, neighborhood: Neighborhood::Moore(1), odds: Odds::NONE, sides: Sides::NONE }
+    }
+
+    pub fn with_neighborhood(self, neighborhood: Neighborhood) -> Rule {
+        Rule { neighborhood, ..self }
+    }
+
+    pub fn is_stochastic(&self) -> bool {
+        !self.odds.is_none()
+    }
+
+    pub fn is_directional(&self) -> bool {
+        !self.sides.is_none()
+    }
+
+    // next state of a cell given its current state and alive neighbor count - ignores the side conditions
+    #[inline]
+    pub fn next(&self, state: u8, count: usize) -> u8 {
+        match state {
+            DEAD => if self.birth.contains(count) { ALIVE } else { DEAD },
+            ALIVE if self.survival.contains(count) => ALIVE,
+            // failed survival or already dying => one step closer to dead
+            _ => self.decay(state),
+        }
+    }
+
+    // same given the alive neighbors below, beside and above the cell - takes the side conditions into account
+    #[inline]
+    pub fn next_sided(&self, state: u8, sides: [usize; 3]) -> u8 {
+        let next = self.next(state, sides.iter().sum());
+        let conditions = if state == DEAD { &self.sides.birth } else { &self.sides.survival };
+        if next == ALIVE && !sides_allow(conditions, sides) {
+            return self.fail(state);
+        }
+        next
+    }
+
+    // same for a stochastic rule - noise is the cell's random number (see noise)
+    #[inline]
+    pub fn next_noisy(&self, state: u8, sides: [usize; 3], noise: u64) -> u8 {
+        let mut next = self.next_sided(state, sides);
+        // a birth / survival only happens with its chance - otherwise it's as if the count wasn't in the rule
+        let count = sides.iter().sum();
+        let chance = match state {
+            DEAD => chance_of(&self.odds.birth, count),
+            ALIVE => chance_of(&self.odds.survival, count),
+            _ => CERTAIN,
+        };
+        if next == ALIVE && !hits(noise as u32, chance) {
+            next = self.fail(state);
+        }
+        if hits((noise >> 32) as u32, self.odds.temperature) {
+            next = if next == DEAD { ALIVE } else { DEAD };
+        }
+        next
+    }
+
+    // a dead cell that isn't born stays dead, an alive one that doesn't survive starts dying
+    #[inline]
+    fn fail(&self, state: u8) -> u8 {
+        if state == DEAD { DEAD } else { self.decay(state) }
+    }
+
+    #[inline]
+    fn decay(&self, state: u8) -> u8 {
+        if state + 1 >= self.states { DEAD } else { state + 1 }
+    }
+
+    // display name of the preset this rule matches, if any
+    pub fn preset_name(&self) -> Option<&'static str> {
+        Rule::PRESETS.iter().find(|(_, r)| r == self).map(|(name, _)| *name)
+    }
+
+    // "B5,6/S5-7" or "S5-7/B5,6" - either half may be empty e.g. "B5/S"
+    // an optional "C5" part gives the state count of a Generations rule,
+    // an optional "M2" / "N" / "W2,1,0" / "FCC" / "{x,y,z;...}" part the neighborhood
+    // and an optional "T0.001" part the temperature - counts can have chances too, e.g. "B5:0.5,6",
+    // and be followed by side conditions, e.g. "B5-6D2-9" (see Sides)
+    fn parse_bs(parts: &[&str], src: &str) -> Result<Rule, RuleError> {
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
+        let mut neighborhood = None;
+        let mut temperature = None;
+        for part in parts {
+            let mut chars = part.chars();
+            match chars.next() {
+                _ if Neighborhood::is_lattice(part) && neighborhood.is_none() => neighborhood = Some(part.parse()?),
+                Some('B' | 'b') if birth.is_none() => birth = Some(parse_condition(chars.as_str())?),
+                Some('S' | 's') if survival.is_none() => survival = Some(parse_condition(chars.as_str())?),
+                Some('C' | 'c') if states.is_none() => states = Some(parse_states(chars.as_str())?),
+                Some('M' | 'm' | 'N' | 'n' | 'V' | 'v' | 'W' | 'w' | '{') if neighborhood.is_none() => neighborhood = Some(part.parse()?),
+                Some('T' | 't') if temperature.is_none() => temperature = Some(parse_chance(chars.as_str())?),
+                _ => return Err(RuleError::BadFormat(src.to_string())),
+            }
+        }
+        match (birth, survival) {
+            (Some((birth, birth_odds, birth_sides)), Some((survival, survival_odds, survival_sides))) => {
+                let odds = Odds { birth: birth_odds, survival: survival_odds, temperature: temperature.unwrap_or(0) };
+                let sides = Sides { birth: birth_sides, survival: survival_sides };
+                Ok(Rule { odds, sides, ..Rule::generations(birth, survival, states.unwrap_or(2)).with_neighborhood(neighborhood.unwrap_or_default()) })
+            }
+            _ => Err(RuleError::BadFormat(src.to_string())),
+        }
+    }
+
+    // "4-5/5/2/M" => survival/birth/states/neighborhood, trailing parts optional
+    fn parse_sbn(parts: &[&str], src: &str) -> Result<Rule, RuleError> {
+        if parts.len() < 2 || parts.len() > 4 {
+            return Err(RuleError::BadFormat(src.to_string()));
+        }
+        let (survival, survival_odds, survival_sides) = parse_condition(parts[0])?;
+        let (birth, birth_odds, birth_sides) = parse_condition(parts[1])?;
+
+        let states = match parts.get(2) {
+            Some(states) => parse_states(states)?,
+            None => 2,
+        };
+        let neighborhood = match parts.get(3) {
+            Some(neighborhood) => neighborhood.parse()?,
+            None => Neighborhood::default(),
+        };
+        let odds = Odds { birth: birth_odds, survival: survival_odds, temperature: 0 };
+        let sides = Sides { birth: birth_sides, survival: survival_sides };
+        Ok(Rule { odds, sides, ..Rule::generations(birth, survival, states).with_neighborhood(neighborhood) })
+    }
+}
+
+fn parse_states(s: &str) -> Result<u8, RuleError> {
+    match s.trim().parse::<u8>() {
+        Ok(n) if n >= 2 => Ok(n),
+        _ => Err(RuleError::BadStates(s.to_string())),
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    fn from_str(s: &str) -> Result<Rule, RuleError> {
+        let parts: Vec<&str> = s.trim().split('/').map(str::trim).collect();
+        if parts.iter().any(|p| p.starts_with(['B', 'b', 'S', 's']) && !Neighborhood::is_lattice(p)) {
+            Rule::parse_bs(&parts, s)
+        } else {
+            Rule::parse_sbn(&parts, s)
+        }
+    }
+}
+
+// the counts of the set with their chances - runs of counts with the same chance share it, e.g. "4,5-6:0.5"
+fn write_odds(f: &mut fmt::Formatter, set: &CountSet, odds: &[(usize, u32)]) -> fmt::Result {
+    if odds.is_empty() {
+        return write!(f, "{}", set);
+    }
+    let counts: Vec<usize> = set.iter().collect();
+    let mut i = 0;
+    while i < counts.len() {
+        let chance = chance_of(odds, counts[i]);
+        let mut j = i;
+        while j + 1 < counts.len() && counts[j + 1] == counts[j] + 1 && chance_of(odds, counts[j + 1]) == chance {
+            j += 1;
+        }
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        if i == j {
+            write!(f, "{}", counts[i])?;
+        } else {
+            write!(f, "{}-{}", counts[i], counts[j])?;
+        }
+        if chance != CERTAIN {
+            write!(f, ":{}", chance as f64 / CERTAIN as f64)?;
+        }
+        i = j + 1;
+    }
+    Ok(())
+}
+
+// counts of a birth / survival part followed by its side groups - counts left out when any are fine and there are groups
+fn write_condition(f: &mut fmt::Formatter, set: &CountSet, odds: &[(usize, u32)], sides: &[Option<CountSet>; 3]) -> fmt::Result {
+    if sides.iter().all(Option::is_none) || *set != CountSet::range(0, MAX_COUNT) || !odds.is_empty() {
+        write_odds(f, set, odds)?;
+    }
+    for (name, set) in SIDE_NAMES.iter().zip(sides) {
+        if let Some(set) = set {
+            write!(f, "{}{}", name, set)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        write_condition(f, &self.birth, &self.odds.birth, &self.sides.birth)?;
+        write!(f, "/S")?;
+        write_condition(f, &self.survival, &self.odds.survival, &self.sides.survival)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        if self.neighborhood != Neighborhood::default() {
+            write!(f, "/{}", self.neighborhood)?;
+        }
+        if self.odds.temperature > 0 {
+            write!(f, "/T{}", self.odds.temperature as f64 / CERTAIN as f64)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(rule: &Rule) {
+        let text = rule.to_string();
+        assert_eq!(text.parse::<Rule>().as_ref(), Ok(rule), "{} doesn't read back", text);
+    }
+
+    #[test]
+    fn presets_round_trip() {
+        for (name, rule) in &Rule::PRESETS {
+            round_trip(rule);
+            assert_eq!(rule.preset_name(), Some(*name));
+        }
+        assert_eq!(Rule::BAYS.to_string(), "B5/S4-5");
+        assert_eq!("B5/S4-5".parse(), Ok(Rule::BAYS));
+        assert_eq!("4/4/5/M".parse(), Ok(Rule::BRAIN));
+        round_trip(&Rule::generations(CountSet::range(4, 5), CountSet::range(2, 3), 10));
+    }
+
+    #[test]
+    fn parses_every_notation() {
+        let rule = |s: &str| s.parse::<Rule>().unwrap_or_else(|e| panic!("{}: {}", s, e));
+        assert_eq!(rule("B5,6/S5-7"), Rule::BALANCED);
+        assert_eq!(rule("s5-7/b5-6"), Rule::BALANCED);
+        assert_eq!(rule("5-7/5-6"), Rule::BALANCED);
+        assert_eq!(rule(" B5 / S4-5 / C2 "), Rule::BAYS);
+        assert_eq!(rule("B1/S/M2"), Rule::new(CountSet::range(1, 1), CountSet::EMPTY).with_neighborhood("M2".parse().unwrap()));
+        assert_eq!(rule("B10-12/S8-14/C3/N3").states, 3);
+
+        // everything else only has to read back the way it was written
+        for s in ["B8-10/S6-12/W2,1,0", "B5-6D3-9/S4-7", "B/SU1-2", "B4/S3-5/FCC", "B3/S2/C4/BCC", "B5:0.5,6/S5-7/T0.001"] {
+            let parsed = rule(s);
+            assert_eq!(parsed.to_string(), s);
+            round_trip(&parsed);
+        }
+        assert_eq!(rule("B5:0.5,6/S5-7").odds.birth, vec![(5, CERTAIN / 2)]);
+        assert_eq!(rule("B4/S3-5/FCC").neighborhood, Neighborhood::Fcc);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let err = |s: &str| s.parse::<Rule>().unwrap_err();
+        assert_eq!(err("B5/Sx"), RuleError::BadCount("x".into()));
+        assert_eq!(err("B6-5/S4"), RuleError::BadRange("6-5".into()));
+        assert_eq!(err("B512/S4"), RuleError::CountTooLarge(512));
+        assert_eq!(err(""), RuleError::BadFormat("".into()));
+        assert_eq!(err("B5"), RuleError::BadFormat("B5".into()));
+        assert_eq!(err("B5/S4/Q"), RuleError::BadFormat("B5/S4/Q".into()));
+        assert_eq!(err("B5/B6/S4"), RuleError::BadFormat("B5/B6/S4".into()));
+        assert_eq!(err("B5D1D2/S4"), RuleError::BadFormat("5D1D2".into()));
+        assert_eq!(err("4/5/6/M/N"), RuleError::BadFormat("4/5/6/M/N".into()));
+        assert_eq!(err("B5/S4/C1"), RuleError::BadStates("1".into()));
+        assert_eq!(err("4/5/256"), RuleError::BadStates("256".into()));
+        assert!(matches!(err("B5/S4/Mx"), RuleError::BadNeighborhood(_)));
+        assert_eq!(err("B5:1.5/S4"), RuleError::BadChance("1.5".into()));
+        assert_eq!(err("B5/S4/T-1"), RuleError::BadChance("-1".into()));
+        assert!(matches!(err("B5/S4/M5"), RuleError::Unsupported(_)));
+    }
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::UVec3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::game::Game;
+use crate::grid::Grid;
+use crate::rule::ALIVE;
+
+// period 2 oscillator of the Balanced and Dense rules - a flat U flipping back and forth
+pub const OSCILLATOR: [[i32; 3]; 5] = [[0, 0, 0], [0, 1, 0], [1, 0, 0], [2, 0, 0], [2, 1, 0]];
+
+// Carter Bays' glider of the B5/S4-5 rule ("Bays" preset) - moves one cell in -y and +z every 4 generations
+pub const GLIDER: [[i32; 3]; 10] = [
+    [0, 1, 0], [0, 1, 1], [1, 0, 0], [1, 0, 1], [1, 2, 1],
+    [2, 0, 0], [2, 0, 1], [2, 2, 1], [3, 1, 0], [3, 1, 1],
+];
+
+// how many gliders Scattered places
+const COPIES: usize = 12;
+// cells between the random values Noise interpolates - about the size of its clumps
+const NOISE_SCALE: usize = 8;
+
+// What the world starts out as - picked separately from the rule
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Seeding {
+    #[default]
+    Random,     // soup with prob chance per cell
+    Empty,      // nothing - e.g. for a loaded pattern on its own
+    Block,      // small solid cube in the center
+    Shell,      // hollow sphere
+    Sphere,     // solid sphere
+    Cross,      // three bars through the center along x, y and z
+    Oscillator, // OSCILLATOR in the center
+    Glider,     // GLIDER in the center
+    Scattered,  // copies of GLIDER at random places
+    CubeSoup(u32), // soup only within a centered cube of that radius (half its side) - 0 => a quarter of the shortest side
+    BallSoup(u32), // soup only within a centered sphere of that radius - same default
+    Falloff,    // soup thinning out from the center to the faces
+    Noise,      // soup with its density following smooth 3d value noise - clumps and voids
+    Mirror(u8), // soup mirrored across 1, 2 or 3 planes through the center - x, then y, then z
+}
+
+impl Seeding {
+    // with their display names - used for the menu
+    pub const ALL: [(&'static str, Seeding); 16] = [
+        ("Random", Seeding::Random),
+        ("Empty", Seeding::Empty),
+        ("Block", Seeding::Block),
+        ("Shell", Seeding::Shell),
+        ("Sphere", Seeding::Sphere),
+        ("Cross", Seeding::Cross),
+        ("Oscillator", Seeding::Oscillator),
+        ("Glider", Seeding::Glider),
+        ("Scattered", Seeding::Scattered),
+        ("Cube soup", Seeding::CubeSoup(0)),
+        ("Ball soup", Seeding::BallSoup(0)),
+        ("Falloff", Seeding::Falloff),
+        ("Noise", Seeding::Noise),
+        ("Mirror", Seeding::Mirror(1)),
+        ("Mirror 2", Seeding::Mirror(2)),
+        ("Mirror 3", Seeding::Mirror(3)),
+    ];
+
+    // the soups with a radius share the name no matter the radius
+    pub fn name(&self) -> &'static str {
+        let kind = self.with_radius(0).unwrap_or(*self);
+        Seeding::ALL.iter().find(|(_, s)| *s == kind).map(|(name, _)| *name).unwrap_or_default()
+    }
+
+    // whether another seed gives another start - the structures always look the same
+    pub fn uses_seed(&self) -> bool {
+        !matches!(
+            self,
+            Seeding::Empty | Seeding::Block | Seeding::Shell | Seeding::Sphere | Seeding::Cross | Seeding::Oscillator | Seeding::Glider
+        )
+    }
+
+    // the same soup with another radius - None for the ones without
+    pub fn with_radius(&self, radius: u32) -> Option<Seeding> {
+        match self {
+            Seeding::CubeSoup(_) => Some(Seeding::CubeSoup(radius)),
+            Seeding::BallSoup(_) => Some(Seeding::BallSoup(radius)),
+            _ => None,
+        }
+    }
+
+    // the starting grid - seed only matters for the random ones
+    pub fn generate(&self, prob: f64, size: UVec3, states: u8, seed: u64) -> Grid {
+        if *self == Seeding::Random {
+            return Game::randomize(prob, size, states, seed);
+        }
+
+        let mut grid = Grid::new(size, states);
+        let mid = size.to_array().map(|v| (v / 2) as i32);
+        // shapes are sized after the shortest side so they fit thin slabs too
+        let short = size.min_element() as i32;
+        let radius = (short / 4).max(1);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let soup_radius = |r: u32| if r == 0 { radius } else { r as i32 };
+
+        match self {
+            Seeding::Random | Seeding::Empty => {}
+            Seeding::Block => {
+                let half = (short / 16).max(1);
+                fill(&mut grid, |p| (0..3).all(|a| (p[a] - mid[a]).abs() <= half));
+            }
+            Seeding::Shell => {
+                fill(&mut grid, |p| {
+                    let d = dist2(p, mid);
+                    d <= radius * radius && d > (radius - 1) * (radius - 1)
+                });
+            }
+            Seeding::Sphere => {
+                fill(&mut grid, |p| dist2(p, mid) <= radius * radius);
+            }
+            Seeding::Cross => {
+                // a cell is on the cross when it's on the center line of two axes
+                fill(&mut grid, |p| {
+                    let on_axis = (0..3).filter(|&a| p[a] == mid[a]).count();
+                    on_axis >= 2 && (0..3).all(|a| (p[a] - mid[a]).abs() <= radius)
+                });
+            }
+            Seeding::Oscillator => place(&mut grid, &OSCILLATOR, mid),
+            Seeding::Glider => place(&mut grid, &GLIDER, mid),
+            Seeding::Scattered => {
+                let dims = size.to_array();
+                for _ in 0..COPIES {
+                    let at = std::array::from_fn(|a| rng.gen_range(0..dims[a].max(1)) as i32);
+                    place(&mut grid, &GLIDER, at);
+                }
+            }
+            Seeding::CubeSoup(r) => {
+                let r = soup_radius(*r);
+                soup(&mut grid, &mut rng, |p| if (0..3).all(|a| (p[a] - mid[a]).abs() <= r) { prob } else { 0. });
+            }
+            Seeding::BallSoup(r) => {
+                let r = soup_radius(*r);
+                soup(&mut grid, &mut rng, |p| if dist2(p, mid) <= r * r { prob } else { 0. });
+            }
+            Seeding::Falloff => {
+                // 4x prob in the center down to nothing at the sphere touching the faces - prob on average within it
+                let edge = (short as f64 / 2.).max(1.);
+                soup(&mut grid, &mut rng, |p| 4. * prob * (1. - (dist2(p, mid) as f64).sqrt() / edge).max(0.));
+            }
+            Seeding::Noise => {
+                let noise = ValueNoise::new(size, &mut rng);
+                // the noise averages 0.5 - so does the density factor
+                soup(&mut grid, &mut rng, |p| 2. * prob * noise.at(p));
+            }
+            Seeding::Mirror(planes) => {
+                // a plain soup with everything past the middle of the mirrored axes copied from the other side
+                grid = Game::randomize(prob, size, states, seed);
+                let dims = size.to_array().map(|v| v as usize);
+                for x in 0..dims[0] {
+                    for y in 0..dims[1] {
+                        for z in 0..dims[2] {
+                            let p = [x, y, z];
+                            let q: [usize; 3] = std::array::from_fn(|a| if a < *planes as usize { p[a].min(dims[a] - 1 - p[a]) } else { p[a] });
+                            if q != p {
+                                grid.set(x, y, z, grid.get(q[0], q[1], q[2]));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+}
+
+// "Ball soup 12" - a radius only for the soups that have one
+impl fmt::Display for Seeding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Seeding::CubeSoup(r) | Seeding::BallSoup(r) if *r > 0 => write!(f, "{} {}", self.name(), r),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for Seeding {
+    type Err = String;
+
+    // the names from ALL, case doesn't matter
+    fn from_str(s: &str) -> Result<Seeding, String> {
+        let s = s.trim();
+        let find = |name: &str| Seeding::ALL.iter().find(|(n, _)| n.eq_ignore_ascii_case(name.trim())).map(|(_, v)| *v);
+        if let Some(seeding) = find(s) {
+            return Ok(seeding);
+        }
+        s.rsplit_once(' ')
+            .and_then(|(name, radius)| find(name)?.with_radius(radius.parse().ok()?))
+            .ok_or_else(|| s.to_string())
+    }
+}
+
+// Smooth noise in 0..1 - random values on a coarse grid every NOISE_SCALE cells, blended in between
+struct ValueNoise {
+    dims: [usize; 3], // coarse grid points per axis
+    values: Vec<f64>,
+}
+
+impl ValueNoise {
+    fn new(size: UVec3, rng: &mut StdRng) -> ValueNoise {
+        let dims = size.to_array().map(|v| v as usize / NOISE_SCALE + 2);
+        let values = (0..dims.iter().product()).map(|_| rng.r#gen::<f64>()).collect();
+        ValueNoise { dims, values }
+    }
+
+    fn at(&self, p: [i32; 3]) -> f64 {
+        let cell = p.map(|v| v as usize / NOISE_SCALE);
+        // smoothstep so the blend has no visible creases at the coarse grid
+        let t = p.map(|v| {
+            let t = (v as usize % NOISE_SCALE) as f64 / NOISE_SCALE as f64;
+            t * t * (3. - 2. * t)
+        });
+        let value = |c: [usize; 3]| self.values[(c[0] * self.dims[1] + c[1]) * self.dims[2] + c[2]];
+
+        let mut sum = 0.;
+        for corner in 0..8 {
+            let offset = [corner & 1, corner >> 1 & 1, corner >> 2 & 1];
+            let weight: f64 = (0..3).map(|a| if offset[a] == 1 { t[a] } else { 1. - t[a] }).product();
+            sum += weight * value(std::array::from_fn(|a| cell[a] + offset[a]));
+        }
+        sum
+    }
+}
+
+// every cell alive with the chance density gives for it
+fn soup(grid: &mut Grid, rng: &mut StdRng, density: impl Fn([i32; 3]) -> f64) {
+    let size = grid.size();
+    for x in 0..size.x as usize {
+        for y in 0..size.y as usize {
+            for z in 0..size.z as usize {
+                if rng.gen_bool(density([x as i32, y as i32, z as i32]).clamp(0., 1.)) {
+                    grid.set(x, y, z, ALIVE);
+                }
+            }
+        }
+    }
+}
+
+fn dist2(p: [i32; 3], q: [i32; 3]) -> i32 {
+    (0..3).map(|a| (p[a] - q[a]) * (p[a] - q[a])).sum()
+}
+
+// sets every cell the predicate holds for alive
+fn fill(grid: &mut Grid, alive: impl Fn([i32; 3]) -> bool) {
+    let size = grid.size();
+    for x in 0..size.x as usize {
+        for y in 0..size.y as usize {
+            for z in 0..size.z as usize {
+                if alive([x as i32, y as i32, z as i32]) {
+                    grid.set(x, y, z, ALIVE);
+                }
+            }
+        }
+    }
+}
+
+// pattern cells relative to at, cells that would end up outside the grid are left out
+fn place(grid: &mut Grid, pattern: &[[i32; 3]], at: [i32; 3]) {
+    let size = grid.size().to_array().map(|v| v as i32);
+    for cell in pattern {
+        let p: [i32; 3] = std::array::from_fn(|a| at[a] + cell[a]);
+        if (0..3).all(|a| p[a] >= 0 && p[a] < size[a]) {
+            grid.set(p[0] as usize, p[1] as usize, p[2] as usize, ALIVE);
+        }
+    }
+}
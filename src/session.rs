@@ -0,0 +1,233 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::UVec3;
+
+use crate::game::{Boundary, Game};
+use crate::pattern::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::seeding::Seeding;
+
+// largest world a session may ask for - the grid gets allocated up front
+const MAX_CELLS: usize = 1 << 30;
+
+// Everything needed to pick a game up again later - plain text:
+//
+//   #GOL3D session
+//   generation = 120
+//   size = 64, 64, 64
+//   rule = B5-6/S5-7
+//   boundary = Dead
+//   prob = 0.05
+//   speed = 1
+//   seed = 42
+//   seeding = Random
+//   world = 3, 0, 12
+//   x = 58, y = 64, z = 40, rule = B5-6/S5-7
+//   ...!
+//
+// world is the corner of the cells that follow as a .rle3 pattern (see pattern.rs).
+// A pattern stamped in on every reset (Game::pattern) follows the same way after "stamp = x, y, z"
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub generation: usize,
+    pub size: UVec3,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub prob: f64,
+    pub speed: f32,
+    pub seed: u64,
+    pub seeding: Seeding,
+    pub world: (Pattern, [i64; 3]),
+    pub stamp: Option<(Pattern, [i64; 3])>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    Io(String),
+    Missing(&'static str),   // key that isn't there
+    BadValue(String, String), // key and the value it couldn't make sense of
+    Pattern(PatternError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Io(e) => write!(f, "couldn't access session file: {}", e),
+            SessionError::Missing(key) => write!(f, "session has no '{}'", key),
+            SessionError::BadValue(key, value) => write!(f, "'{}' is not a valid {}", value, key),
+            SessionError::Pattern(e) => write!(f, "bad cells in session: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<PatternError> for SessionError {
+    fn from(e: PatternError) -> SessionError {
+        SessionError::Pattern(e)
+    }
+}
+
+impl Session {
+    pub fn of(game: &Game) -> Session {
+        Session {
+            generation: game.generation,
+            size: game.size,
+            rule: game.rule.clone(),
+            boundary: game.boundary,
+            prob: game.prob,
+            speed: game.speed,
+            seed: game.seed,
+            seeding: game.seeding,
+            world: game.capture(),
+            stamp: game.pattern.clone(),
+        }
+    }
+
+    pub fn into_game(self) -> Game {
+        // starts empty - the saved world replaces the soup anyway, seeding and seed are only kept for a reset
+        let mut game = Game::new(self.size, self.prob, self.rule, self.speed, self.boundary, self.seed, Seeding::Empty);
+        game.seeding = self.seeding;
+        game.pattern = self.stamp;
+        let (world, offset) = self.world;
+        game.load_world(&world, offset, self.generation);
+        game
+    }
+
+    pub fn load(path: &str) -> Result<Session, SessionError> {
+        std::fs::read_to_string(path).map_err(|e| SessionError::Io(e.to_string()))?.parse()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SessionError> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir).map_err(|e| SessionError::Io(e.to_string()))?;
+        }
+        std::fs::write(path, self.to_string()).map_err(|e| SessionError::Io(e.to_string()))
+    }
+}
+
+fn write_pattern(f: &mut fmt::Formatter, key: &str, (pattern, offset): &(Pattern, [i64; 3])) -> fmt::Result {
+    writeln!(f, "{} = {}, {}, {}", key, offset[0], offset[1], offset[2])?;
+    write!(f, "{}", pattern)
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "#GOL3D session")?;
+        writeln!(f, "generation = {}", self.generation)?;
+        writeln!(f, "size = {}, {}, {}", self.size.x, self.size.y, self.size.z)?;
+        writeln!(f, "rule = {}", self.rule)?;
+        writeln!(f, "boundary = {}", self.boundary.name())?;
+        writeln!(f, "prob = {}", self.prob)?;
+        writeln!(f, "speed = {}", self.speed)?;
+        writeln!(f, "seed = {}", self.seed)?;
+        writeln!(f, "seeding = {}", self.seeding)?;
+        write_pattern(f, "world", &self.world)?;
+        if let Some(stamp) = &self.stamp {
+            write_pattern(f, "stamp", stamp)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, SessionError> {
+    value.parse().map_err(|_| SessionError::BadValue(key.to_string(), value.to_string()))
+}
+
+fn parse_triple<T: FromStr>(key: &str, value: &str) -> Result<[T; 3], SessionError> {
+    let bad = || SessionError::BadValue(key.to_string(), value.to_string());
+    let parts: Vec<T> = value.split(',').map(|v| v.trim().parse().map_err(|_| bad())).collect::<Result<_, _>>()?;
+    parts.try_into().map_err(|_| bad())
+}
+
+impl FromStr for Session {
+    type Err = SessionError;
+
+    fn from_str(s: &str) -> Result<Session, SessionError> {
+        let mut values: Vec<(&str, &str)> = Vec::new();
+        let mut patterns: Vec<(&str, [i64; 3], Pattern)> = Vec::new();
+
+        let mut lines = s.lines().filter(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+        while let Some(line) = lines.next() {
+            let (key, value) = line.split_once('=').ok_or_else(|| SessionError::BadValue("line".to_string(), line.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            if key == "world" || key == "stamp" {
+                // the pattern runs up to and including the line with its '!'
+                let mut text = String::new();
+                for line in lines.by_ref() {
+                    text.push_str(line);
+                    text.push('\n');
+                    if line.contains('!') {
+                        break;
+                    }
+                }
+                patterns.push((key, parse_triple(key, value)?, text.parse()?));
+            } else {
+                values.push((key, value));
+            }
+        }
+
+        let get = |key: &'static str| values.iter().find(|(k, _)| *k == key).map(|(_, v)| *v).ok_or(SessionError::Missing(key));
+        let mut pattern = |key: &'static str| patterns.iter().position(|(k, _, _)| *k == key).map(|i| {
+            let (_, offset, pattern) = patterns.swap_remove(i);
+            (pattern, offset)
+        });
+
+        let size = get("size")?;
+        let [x, y, z]: [u32; 3] = parse_triple("size", size)?;
+        if [x, y, z].iter().try_fold(1usize, |v, &d| v.checked_mul(d as usize)).is_none_or(|v| v > MAX_CELLS) {
+            return Err(SessionError::BadValue("size".to_string(), size.to_string()));
+        }
+        let boundary = get("boundary")?;
+        let rule: Rule = parse("rule", get("rule")?)?;
+        // anything else would panic once the game is built - the soup's gen_bool and the step timer
+        let prob = get("prob")?;
+        let prob = parse("prob", prob).ok().filter(|p: &f64| (0. ..=1.).contains(p)).ok_or_else(|| SessionError::BadValue("prob".to_string(), prob.to_string()))?;
+        let speed = get("speed")?;
+        let speed = parse("speed", speed).ok().filter(|s: &f32| s.is_finite() && *s > 0.).ok_or_else(|| SessionError::BadValue("speed".to_string(), speed.to_string()))?;
+        let world = pattern("world").ok_or(SessionError::Missing("world"))?;
+        let stamp = pattern("stamp");
+        for (cells, _) in std::iter::once(&world).chain(&stamp) {
+            cells.check_states(rule.states)?;
+        }
+        Ok(Session {
+            generation: parse("generation", get("generation")?)?,
+            size: UVec3::new(x, y, z),
+            rule,
+            boundary: Boundary::ALL.iter().find(|(name, _)| *name == boundary).map(|(_, b)| *b)
+                .ok_or_else(|| SessionError::BadValue("boundary".to_string(), boundary.to_string()))?,
+            prob,
+            speed,
+            seed: parse("seed", get("seed")?)?,
+            seeding: parse("seeding", get("seeding")?)?,
+            world,
+            stamp,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a saved game has to come back with the same cells, and its seeding still has to give the same reset
+    #[test]
+    fn round_trips_a_running_game() {
+        for boundary in [Boundary::Dead, Boundary::Unbounded] {
+            let mut game = Game::new(UVec3::new(12, 10, 8), 0.3, Rule::BALANCED, 1., boundary, 42, Seeding::CubeSoup(3));
+            for _ in 0..5 {
+                game.advance_state();
+            }
+            let text = Session::of(&game).to_string();
+            let mut loaded = text.parse::<Session>().unwrap().into_game();
+            assert_eq!(loaded.generation, 5);
+            assert_eq!(loaded.seeding, Seeding::CubeSoup(3));
+            assert_eq!(loaded.capture(), game.capture(), "{}", boundary.name());
+
+            game.reset();
+            loaded.reset();
+            assert_eq!(loaded.capture(), game.capture(), "{} after a reset", boundary.name());
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use bevy::prelude::UVec3;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+
+use crate::chunks::CHUNK;
+use crate::grid::{coords, linear_index, Grid};
+use crate::rule::{noise, Rule, ALIVE, DEAD};
+
+// cells per chunk
+pub const CHUNK_CELLS: usize = CHUNK * CHUNK * CHUNK;
+pub const CHUNK_SIZE: UVec3 = UVec3::splat(CHUNK as u32);
+
+pub type ChunkPos = [i32; 3];
+
+// Unbounded world - only CHUNK^3 blocks with something in them are stored.
+// Chunks get allocated as activity spreads into them and dropped again once everything in them is dead.
+// Rules with birth on 0 neighbors would fill all of space - here they only grow by the neighbor reach each step
+#[derive(Clone, Debug, Default)]
+pub struct SparseWorld {
+    chunks: HashMap<ChunkPos, Vec<u8>>, // cell states in linear_index order within the chunk
+}
+
+impl SparseWorld {
+    // the grid's block placed at the origin
+    pub fn from_grid(grid: &Grid) -> SparseWorld {
+        let mut world = SparseWorld::default();
+        let size = grid.size();
+        for x in 0..size.x as usize {
+            for y in 0..size.y as usize {
+                for z in 0..size.z as usize {
+                    let state = grid.get(x, y, z);
+                    if state != DEAD {
+                        world.set([x as i64, y as i64, z as i64], state);
+                    }
+                }
+            }
+        }
+        world
+    }
+
+    fn split([x, y, z]: [i64; 3]) -> (ChunkPos, usize) {
+        let c = CHUNK as i64;
+        let pos = [x.div_euclid(c) as i32, y.div_euclid(c) as i32, z.div_euclid(c) as i32];
+        let local = linear_index(x.rem_euclid(c) as usize, y.rem_euclid(c) as usize, z.rem_euclid(c) as usize, CHUNK_SIZE);
+        (pos, local)
+    }
+
+    pub fn get(&self, cell: [i64; 3]) -> u8 {
+        let (pos, local) = SparseWorld::split(cell);
+        self.chunks.get(&pos).map_or(DEAD, |cells| cells[local])
+    }
+
+    pub fn set(&mut self, cell: [i64; 3], state: u8) {
+        let (pos, local) = SparseWorld::split(cell);
+        if state == DEAD && !self.chunks.contains_key(&pos) {
+            return;
+        }
+        self.chunks.entry(pos).or_insert_with(|| vec![DEAD; CHUNK_CELLS])[local] = state;
+    }
+
+    // live chunks and their cells
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkPos, &Vec<u8>)> {
+        self.chunks.iter()
+    }
+
+    pub fn chunk(&self, pos: &ChunkPos) -> Option<&Vec<u8>> {
+        self.chunks.get(pos)
+    }
+
+    // coordinates and states of all cells that aren't dead
+    pub fn iter_cells(&self) -> impl Iterator<Item = ([i64; 3], u8)> + '_ {
+        let c = CHUNK as i64;
+        self.chunks.iter().flat_map(move |(pos, cells)| {
+            cells.iter().enumerate().filter(|(_, s)| **s != DEAD).map(move |(i, &state)| {
+                let (x, y, z) = coords(i, CHUNK_SIZE);
+                ([pos[0] as i64 * c + x as i64, pos[1] as i64 * c + y as i64, pos[2] as i64 * c + z as i64], state)
+            })
+        })
+    }
+
+    // coordinates of all alive cells
+    pub fn iter_alive(&self) -> impl Iterator<Item = [i64; 3]> + '_ {
+        self.iter_cells().filter(|&(_, state)| state == ALIVE).map(|(p, _)| p)
+    }
+
+    pub fn contains_chunk(&self, pos: &ChunkPos) -> bool {
+        self.chunks.contains_key(pos)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    // hash of all cell states - independent of the order chunks are stored in
+    pub fn state_hash(&self) -> u64 {
+        self.chunks.iter().fold(0, |acc, chunk| {
+            let mut hasher = DefaultHasher::new();
+            chunk.hash(&mut hasher);
+            acc ^ hasher.finish()
+        })
+    }
+
+    // advances one generation and returns how many chunks were evaluated -
+    // seed and generation only matter for stochastic rules (see rule::noise),
+    // their temperature only flips cells in the chunks that get evaluated
+    pub fn step(&mut self, rule: &Rule, neighbors: &[([isize; 3], usize)], seed: u64, generation: usize) -> usize {
+        let reach = neighbors.iter().flat_map(|(d, _)| d).map(|d| d.unsigned_abs()).max().unwrap_or(0);
+        let r = reach.div_ceil(CHUNK) as i32;
+
+        // every live chunk plus everything within reach of it could have something alive next
+        let mut candidates = HashSet::new();
+        for pos in self.chunks.keys() {
+            for dx in -r..=r {
+                for dy in -r..=r {
+                    for dz in -r..=r {
+                        candidates.insert([pos[0] + dx, pos[1] + dy, pos[2] + dz]);
+                    }
+                }
+            }
+        }
+        let candidates: Vec<ChunkPos> = candidates.into_iter().collect();
+
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let this = &*self;
+        let results = pool.scope(|s| {
+            for &pos in &candidates {
+                s.spawn(async move { (pos, this.step_chunk(pos, rule, neighbors, seed, generation)) });
+            }
+        });
+
+        // empty chunks are not kept
+        self.chunks = results.into_iter().filter_map(|(pos, cells)| Some((pos, cells?))).collect();
+        candidates.len()
+    }
+
+    // next states of a chunk - None if everything in it is dead
+    fn step_chunk(&self, pos: ChunkPos, rule: &Rule, neighbors: &[([isize; 3], usize)], seed: u64, generation: usize) -> Option<Vec<u8>> {
+        // the chunk and its direct neighbors, looked up once instead of per cell
+        let view: [Option<&Vec<u8>>; 27] = std::array::from_fn(|i| {
+            let (a, b, c) = coords(i, UVec3::splat(3));
+            self.chunks.get(&[pos[0] + a as i32 - 1, pos[1] + b as i32 - 1, pos[2] + c as i32 - 1])
+        });
+        let c = CHUNK as i64;
+        let origin = pos.map(|p| p as i64 * c);
+        let at = |p: [i64; 3]| -> u8 {
+            // p is relative to the chunk origin
+            let off = p.map(|v| v.div_euclid(c) + 1);
+            if off.iter().all(|o| (0..3).contains(o)) {
+                let chunk = view[linear_index(off[0] as usize, off[1] as usize, off[2] as usize, UVec3::splat(3))];
+                let local = linear_index(p[0].rem_euclid(c) as usize, p[1].rem_euclid(c) as usize, p[2].rem_euclid(c) as usize, CHUNK_SIZE);
+                chunk.map_or(DEAD, |cells| cells[local])
+            } else {
+                self.get([origin[0] + p[0], origin[1] + p[1], origin[2] + p[2]])
+            }
+        };
+
+        let mut cells = vec![DEAD; CHUNK_CELLS];
+        let mut any = false;
+        let lattice = rule.neighborhood.lattice();
+        for x in 0..c {
+            for y in 0..c {
+                for z in 0..c {
+                    // cells off the lattice stay dead
+                    if !lattice.contains([origin[0] + x, origin[1] + y, origin[2] + z]) {
+                        continue;
+                    }
+                    // below, beside and above - like Game::count_neighbors
+                    let mut sides = [0; 3];
+                    for &([dx, dy, dz], w) in neighbors {
+                        if at([x + dx as i64, y + dy as i64, z + dz as i64]) == ALIVE {
+                            sides[(dy.signum() + 1) as usize] += w;
+                        }
+                    }
+                    let state = if rule.is_stochastic() {
+                        let cell = [origin[0] + x, origin[1] + y, origin[2] + z];
+                        rule.next_noisy(at([x, y, z]), sides, noise(seed, generation, cell))
+                    } else {
+                        rule.next_sided(at([x, y, z]), sides)
+                    };
+                    any |= state != DEAD;
+                    cells[linear_index(x as usize, y as usize, z as usize, CHUNK_SIZE)] = state;
+                }
+            }
+        }
+        any.then_some(cells)
+    }
+}
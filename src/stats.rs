@@ -0,0 +1,106 @@
+use std::fmt::Write;
+
+// first line of the CSV - one column per field of GenStats, the bounds split up per axis
+const CSV_COLUMNS: &str = "generation,population,births,deaths,density,min_x,min_y,min_z,max_x,max_y,max_z";
+
+// numbers of a single generation
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GenStats {
+    pub generation: usize,
+    pub population: usize, // alive cells - dying ones of Generations rules don't count
+    pub births: usize,     // cells that became alive since the previous row
+    pub deaths: usize,     // cells that stopped being alive since the previous row
+    pub density: f64,      // population / cells of the world
+    pub bounds: Option<([i64; 3], [i64; 3])>, // inclusive bounding box of the alive cells - None if there are none
+}
+
+impl GenStats {
+    // from the coordinates of all alive cells
+    pub fn measure(generation: usize, alive: impl Iterator<Item = [i64; 3]>, cells: usize, births: usize, deaths: usize) -> GenStats {
+        let mut population = 0;
+        let mut bounds: Option<([i64; 3], [i64; 3])> = None;
+        for cell in alive {
+            population += 1;
+            bounds = Some(match bounds {
+                None => (cell, cell),
+                Some((lo, hi)) => (
+                    std::array::from_fn(|a| lo[a].min(cell[a])),
+                    std::array::from_fn(|a| hi[a].max(cell[a])),
+                ),
+            });
+        }
+        GenStats {
+            generation,
+            population,
+            births,
+            deaths,
+            density: if cells == 0 { 0. } else { population as f64 / cells as f64 },
+            bounds,
+        }
+    }
+
+    // the row's line of the CSV - bounds are left empty when nothing is alive
+    pub fn csv_row(&self) -> String {
+        let mut row = format!("{},{},{},{},{}", self.generation, self.population, self.births, self.deaths, self.density);
+        match self.bounds {
+            Some((lo, hi)) => {
+                let _ = write!(row, ",{},{},{},{},{},{}", lo[0], lo[1], lo[2], hi[0], hi[1], hi[2]);
+            }
+            None => row.push_str(",,,,,,"),
+        }
+        row
+    }
+}
+
+// Time series of GenStats - one row per computed generation, in order.
+// A jump (see Game::jump_to) shows up as a single row, its births / deaths are over the whole jump
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    series: Vec<GenStats>,
+}
+
+impl Stats {
+    pub fn clear(&mut self) {
+        self.series.clear();
+    }
+
+    // rows from this generation on are replaced - e.g. when running on from an earlier point in the history
+    pub fn push(&mut self, row: GenStats) {
+        let keep = self.series.partition_point(|r| r.generation < row.generation);
+        self.series.truncate(keep);
+        self.series.push(row);
+    }
+
+    pub fn series(&self) -> &[GenStats] {
+        &self.series
+    }
+
+    pub fn last(&self) -> Option<&GenStats> {
+        self.series.last()
+    }
+
+    // the row of that generation - stepping back keeps the later rows until it runs on
+    pub fn at(&self, generation: usize) -> Option<&GenStats> {
+        let i = self.series.partition_point(|r| r.generation < generation);
+        self.series.get(i).filter(|r| r.generation == generation)
+    }
+
+    // header lines (`# key: value`) followed by one line per generation
+    pub fn to_csv(&self, header: &[(&str, String)]) -> String {
+        let mut csv = csv_header(header);
+        for row in &self.series {
+            let _ = writeln!(csv, "{}", row.csv_row());
+        }
+        csv
+    }
+}
+
+// the header lines and the column names - for writing the rows one at a time
+pub fn csv_header(header: &[(&str, String)]) -> String {
+    let mut csv = String::new();
+    for (key, value) in header {
+        let _ = writeln!(csv, "# {}: {}", key, value);
+    }
+    let _ = writeln!(csv, "{}", CSV_COLUMNS);
+    csv
+}